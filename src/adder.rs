@@ -1,24 +1,17 @@
-use sim::{NodeIndex, STANDARD_DELAY, NodeCreator};
+use sim::{NodeIndex, NodeCreator};
 
 use arena::Arena;
 
 use logic_gates::{XorGate, AndGate, OrGate};
 
-pub struct HalfAdder {
-    pub a: NodeIndex,
-    pub b: NodeIndex,
-    pub sum: NodeIndex,
-    pub carry: NodeIndex,
-}
-
-impl HalfAdder {
-    pub fn new(creator: &mut NodeCreator) -> HalfAdder {
-        let different = XorGate::new(creator);
-        let both = AndGate::new(creator);
-        
-        creator.link(different.a, both.a, STANDARD_DELAY);
-        creator.link(different.b, both.b, STANDARD_DELAY);
-        HalfAdder {
+component! {
+    pub struct HalfAdder {
+        parts { different: XorGate, both: AndGate }
+        wires {
+            different.a -- both.a,
+            different.b -- both.b,
+        }
+        ports {
             a: different.a,
             b: different.b,
             sum: different.output,
@@ -27,25 +20,15 @@ impl HalfAdder {
     }
 }
 
-pub struct Adder {
-    pub a: NodeIndex,
-    pub b: NodeIndex,
-    pub carry_in: NodeIndex,
-    pub sum: NodeIndex,
-    pub carry_out: NodeIndex,
-}
-
-impl Adder {
-    pub fn new(creator: &mut NodeCreator) -> Adder {
-        let half_one = HalfAdder::new(creator);
-        let half_two = HalfAdder::new(creator);
-        let either_carry = OrGate::new(creator);
-        
-        creator.link(half_one.carry, either_carry.a, STANDARD_DELAY);
-        creator.link(half_two.carry, either_carry.b, STANDARD_DELAY);
-        creator.link(half_one.sum, half_two.a, STANDARD_DELAY);
-        
-        Adder {
+component! {
+    pub struct Adder {
+        parts { half_one: HalfAdder, half_two: HalfAdder, either_carry: OrGate }
+        wires {
+            half_one.carry -- either_carry.a,
+            half_two.carry -- either_carry.b,
+            half_one.sum -- half_two.a,
+        }
+        ports {
             a: half_one.a,
             b: half_one.b,
             carry_in: half_two.b,
@@ -65,12 +48,14 @@ pub struct RippleCarryAdder {
 }
 impl RippleCarryAdder {
     pub fn new(creator: &mut NodeCreator, bits: usize) -> RippleCarryAdder {
+        creator.begin_component("RippleCarryAdder");
         let adders : Vec<Adder> = range(0, bits).map(|_| { Adder::new(creator) }).collect();
         
         for idx in range(1, bits) {
-            creator.link(adders[idx-1].carry_out, adders[idx].carry_in, STANDARD_DELAY);
+            creator.wire(adders[idx-1].carry_out, adders[idx].carry_in);
         }
         
+        creator.end_component();
         RippleCarryAdder {
             a: adders.iter().map(|&: adder| { adder.a }).collect(),
             b: adders.iter().map(|&: adder| { adder.b }).collect(),
@@ -83,8 +68,8 @@ impl RippleCarryAdder {
 
 #[cfg(test)]
 mod test {
-    use truth_table::check_truth_table;
-    use sim::{NodeCreator};
+    use truth_table::{check_truth_table, check_truth_table_with_delays};
+    use sim::{NodeCreator, DelayLibrary, PropogationDelay};
     use super::{HalfAdder, Adder, RippleCarryAdder};
     
     #[test]
@@ -121,6 +106,26 @@ mod test {
         ]);
     }
 
+    #[test]
+    fn test_add_with_slow_delays() {
+        let delays = DelayLibrary {
+            nand: PropogationDelay(40),
+            wire: PropogationDelay(250),
+            wire_per_fanout: PropogationDelay(30),
+        };
+        check_truth_table_with_delays(delays, |creator: &mut NodeCreator| {
+            let h = Adder::new(creator);
+            
+            ([h.a, h.b, h.carry_in].to_vec(), [h.sum, h.carry_out].to_vec())
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,0,0], &[1,0]),
+            (&[1,1,0], &[0,1]),
+            (&[1,0,1], &[0,1]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+
     #[test]
     fn test_4_bit_add() {
         check_truth_table(|creator: &mut NodeCreator| {
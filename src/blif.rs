@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+
+use sim::{NodeCreator, NodeIndex};
+use netlist::NetNames;
+use logic_gates::{NotGate, NWayAnd, OrGate};
+use storage::DFlipFlop;
+use rom::ConstantBit;
+
+/// Writes everything added to the creator as a BLIF model with one .names per NAND.
+/// Each port is a name and its nodes, least significant bit first; bits of wider ports
+/// are named like "a[3]".
+pub fn write_blif(model_name: &str, creator: &NodeCreator, inputs: &[(&str, &[NodeIndex])], outputs: &[(&str, &[NodeIndex])]) -> Result<String, String> {
+    let mut net_names = NetNames::new(creator);
+    let mut port_lines = Vec::new();
+    let mut covers = Vec::new();
+    
+    for (directive, ports) in vec![(".inputs", inputs), (".outputs", outputs)].into_iter() {
+        let mut line = directive.to_string();
+        for &(name, nodes) in ports.iter() {
+            for (bit, node) in nodes.iter().enumerate() {
+                let bit_name = if nodes.len() == 1 { name.to_string() } else { format!("{}[{}]", name, bit) };
+                line.push_str(&format!(" {}", bit_name)[]);
+                if let Some(existing) = net_names.name_port_bit(*node, bit_name.clone()) {
+                    covers.push(format!(".names {} {}\n1 1", existing, bit_name));
+                }
+            }
+        }
+        port_lines.push(line);
+    }
+    
+    for element in creator.elements().iter() {
+        let nodes = element.get_nodes();
+        let params = element.params();
+        match element.kind() {
+            "Nand" => {
+                let (a, b, output) = (net_names.name(nodes[0]), net_names.name(nodes[1]), net_names.name(nodes[2]));
+                covers.push(format!(".names {} {} {}\n0- 1\n-0 1", a, b, output));
+            },
+            "ConstantBit" => {
+                let node = net_names.name(nodes[0]);
+                covers.push(if params[0] != 0 { format!(".names {}\n1", node) } else { format!(".names {}", node) });
+            },
+            "Pin" => {}, // Only driven from outside, which the ports already cover
+            kind => {
+                return Err(format!("{} elements have no BLIF equivalent", kind));
+            }
+        }
+    }
+    
+    let mut text = format!(".model {}\n", model_name);
+    for line in port_lines.iter().chain(covers.iter()) {
+        text.push_str(&line[]);
+        text.push_str("\n");
+    }
+    text.push_str(".end\n");
+    Ok(text)
+}
+
+/// The signals of a model read from BLIF, by name.
+pub struct BlifModel {
+    pub name: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub signals: HashMap<String, NodeIndex>,
+}
+
+impl BlifModel {
+    pub fn node(&self, name: &str) -> Option<NodeIndex> {
+        self.signals.get(name).map(|node| { *node })
+    }
+    
+    pub fn input_nodes(&self) -> Vec<NodeIndex> {
+        self.inputs.iter().map(|name| { *self.signals.get(name).unwrap() }).collect()
+    }
+    
+    pub fn output_nodes(&self) -> Vec<NodeIndex> {
+        self.outputs.iter().map(|name| { *self.signals.get(name).unwrap() }).collect()
+    }
+}
+
+struct Cover {
+    inputs: Vec<String>,
+    output: String,
+    rows: Vec<(String, bool)>, // An input pattern of 0, 1 and -, and the output it gives
+    line: usize,
+}
+
+struct Latch {
+    input: String,
+    output: String,
+    clock: Option<(bool, String)>, // Whether it takes the rising edge, and its clock. None for the global clock.
+}
+
+/// The signal that clocks latches which name no clock of their own.
+pub const GLOBAL_CLOCK: &'static str = "clock";
+
+fn signal_node(name: &str, signals: &mut HashMap<String, NodeIndex>, creator: &mut NodeCreator) -> NodeIndex {
+    if let Some(node) = signals.get(name) {
+        return *node;
+    }
+    let node = creator.new_named_node(name);
+    signals.insert(name.to_string(), node);
+    node
+}
+
+// A sum of products: each row is an AND of literals, and the output is the OR of the rows,
+// inverted if the rows list where the output is 0.
+fn build_cover(cover: &Cover, signals: &mut HashMap<String, NodeIndex>, creator: &mut NodeCreator) -> Result<(), String> {
+    let inputs : Vec<NodeIndex> = cover.inputs.iter().map(|name| { signal_node(&name[], signals, creator) }).collect();
+    let output = signal_node(&cover.output[], signals, creator);
+    
+    let on_set = match cover.rows.first() {
+        Some(&(_, on_set)) => on_set,
+        None => {
+            let zero = ConstantBit::new(false, creator);
+            creator.wire(zero.node, output);
+            return Ok(());
+        }
+    };
+    
+    let mut inverted : Vec<Option<NodeIndex>> = inputs.iter().map(|_| { None }).collect();
+    let mut products = Vec::new();
+    for &(ref pattern, row_output) in cover.rows.iter() {
+        if row_output != on_set {
+            return Err(format!("line {}: a cover must list only 1s or only 0s", cover.line));
+        }
+        
+        let mut literals = Vec::new();
+        for (index, symbol) in pattern.chars().enumerate() {
+            match symbol {
+                '1' => { literals.push(inputs[index]); },
+                '0' => {
+                    if inverted[index].is_none() {
+                        let not = NotGate::new(creator);
+                        creator.wire(inputs[index], not.input);
+                        inverted[index] = Some(not.output);
+                    }
+                    literals.push(inverted[index].unwrap());
+                },
+                '-' => {},
+                _ => { return Err(format!("line {}: bad cover symbol {}", cover.line, symbol)); }
+            }
+        }
+        
+        products.push(match literals.len() {
+            0 => ConstantBit::new(true, creator).node,
+            1 => literals[0],
+            count => {
+                let and = NWayAnd::new(creator, count);
+                creator.multiwire(&literals[], &and.inputs[]);
+                and.output
+            }
+        });
+    }
+    
+    let mut sum = products[0];
+    for product in products[1..].iter() {
+        let or = OrGate::new(creator);
+        creator.wire(sum, or.a);
+        creator.wire(*product, or.b);
+        sum = or.output;
+    }
+    if !on_set {
+        let not = NotGate::new(creator);
+        creator.wire(sum, not.input);
+        sum = not.output;
+    }
+    creator.wire(sum, output);
+    Ok(())
+}
+
+/// Builds the first model of a BLIF file. Latches become DFlipFlops, which start out
+/// unknown whatever initial value the latch asks for.
+pub fn read_blif(text: &str, creator: &mut NodeCreator) -> Result<BlifModel, String> {
+    let mut name = None;
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut covers : Vec<Cover> = Vec::new();
+    let mut latches = Vec::new();
+    let mut in_cover = false;
+    
+    // Lines ending in \ continue on the next line, and # starts a comment.
+    let mut lines = Vec::new();
+    let mut continued : Option<(usize, String)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let (start, mut joined) = continued.take().unwrap_or((index + 1, String::new()));
+        joined.push_str(line.trim_right().trim_right_matches('\\'));
+        joined.push(' ');
+        if line.trim_right().ends_with("\\") {
+            continued = Some((start, joined));
+        } else {
+            lines.push((start, joined));
+        }
+    }
+    
+    for &(line_number, ref line) in lines.iter() {
+        let words : Vec<&str> = line.split(' ').map(|word| { word.trim() }).filter(|word| { !word.is_empty() }).collect();
+        if words.is_empty() {
+            continue;
+        }
+        
+        if !words[0].starts_with(".") {
+            if !in_cover {
+                return Err(format!("line {}: expected a directive", line_number));
+            }
+            let cover = covers.last_mut().unwrap();
+            let (pattern, output) = match (cover.inputs.len(), &words[]) {
+                (0, [output]) => ("", output),
+                (_, [pattern, output]) if pattern.len() == cover.inputs.len() => (pattern, output),
+                _ => { return Err(format!("line {}: expected a cover row for {} inputs", line_number, cover.inputs.len())); }
+            };
+            let output = match output {
+                "1" => true,
+                "0" => false,
+                _ => { return Err(format!("line {}: bad cover output {}", line_number, output)); }
+            };
+            cover.rows.push((pattern.to_string(), output));
+            continue;
+        }
+        
+        in_cover = false;
+        match words[0] {
+            ".model" if words.len() == 2 => {
+                if name.is_some() {
+                    break; // Only the first model is read
+                }
+                name = Some(words[1].to_string());
+            },
+            ".inputs" => { inputs.extend(words[1..].iter().map(|word| { word.to_string() })); },
+            ".outputs" => { outputs.extend(words[1..].iter().map(|word| { word.to_string() })); },
+            ".names" if words.len() >= 2 => {
+                covers.push(Cover {
+                    inputs: words[1..words.len() - 1].iter().map(|word| { word.to_string() }).collect(),
+                    output: words[words.len() - 1].to_string(),
+                    rows: Vec::new(),
+                    line: line_number,
+                });
+                in_cover = true;
+            },
+            ".latch" if words.len() >= 3 => {
+                let clock = match &words[3..] {
+                    [] | [_] | ["re", "NIL"] | ["re", "NIL", _] => None,
+                    ["re", clock] | ["re", clock, _] => Some((true, clock.to_string())),
+                    ["fe", clock] | ["fe", clock, _] => Some((false, clock.to_string())),
+                    _ => { return Err(format!("line {}: only edge-triggered latches are supported", line_number)); }
+                };
+                latches.push(Latch {
+                    input: words[1].to_string(),
+                    output: words[2].to_string(),
+                    clock: clock,
+                });
+            },
+            ".end" => { break; },
+            directive => { return Err(format!("line {}: {} is not supported", line_number, directive)); }
+        }
+    }
+    
+    let name = try!(name.ok_or("no .model".to_string()));
+    creator.begin_component(&name[]);
+    let signals = build_model(&mut inputs, &outputs[], &covers[], &latches[], creator);
+    creator.end_component();
+    
+    Ok(BlifModel {
+        name: name,
+        inputs: inputs,
+        outputs: outputs,
+        signals: try!(signals),
+    })
+}
+
+// Builds the covers and latches, returning the node of every signal. Latches on the global
+// clock add it to the inputs.
+fn build_model(inputs: &mut Vec<String>, outputs: &[String], covers: &[Cover], latches: &[Latch], creator: &mut NodeCreator) -> Result<HashMap<String, NodeIndex>, String> {
+    let mut signals = HashMap::new();
+    for signal in inputs.iter().chain(outputs.iter()) {
+        signal_node(&signal[], &mut signals, creator);
+    }
+    
+    for cover in covers.iter() {
+        try!(build_cover(cover, &mut signals, creator));
+    }
+    
+    for latch in latches.iter() {
+        let ff = DFlipFlop::new(creator);
+        let input = signal_node(&latch.input[], &mut signals, creator);
+        let output = signal_node(&latch.output[], &mut signals, creator);
+        creator.wire(input, ff.data);
+        creator.wire(ff.q, output);
+        
+        match latch.clock {
+            Some((rising, ref clock)) => {
+                let clock = signal_node(&clock[], &mut signals, creator);
+                if rising {
+                    creator.wire(clock, ff.clock);
+                } else {
+                    let not = NotGate::new(creator);
+                    creator.wire(clock, not.input);
+                    creator.wire(not.output, ff.clock);
+                }
+            },
+            None => {
+                if !inputs.iter().any(|input| { &input[] == GLOBAL_CLOCK }) {
+                    inputs.push(GLOBAL_CLOCK.to_string());
+                }
+                let clock = signal_node(GLOBAL_CLOCK, &mut signals, creator);
+                creator.wire(clock, ff.clock);
+            }
+        }
+    }
+    Ok(signals)
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay};
+    use truth_table::check_truth_table;
+    use adder::Adder;
+    use pin::Pin;
+    use super::{read_blif, write_blif};
+    
+    #[test]
+    fn test_read_covers() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let model = read_blif("
+.model majority
+.inputs a b c
+.outputs carry parity
+.names a b c carry
+11- 1
+1-1 1
+-11 1
+.names a b c parity
+100 1
+010 1
+001 1
+111 1
+.end
+", creator).unwrap();
+            (model.input_nodes(), model.output_nodes())
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,0,0], &[0,1]),
+            (&[0,1,0], &[0,1]),
+            (&[1,1,0], &[1,0]),
+            (&[0,0,1], &[0,1]),
+            (&[1,0,1], &[1,0]),
+            (&[0,1,1], &[1,0]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_off_set_and_constants() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let model = read_blif("
+.model misc # Comments and continued lines are allowed
+.inputs a b
+.outputs nor one zero
+.names a \\
+  b nor
+1- 0
+-1 0
+.names one
+1
+.names zero
+.end
+", creator).unwrap();
+            (model.input_nodes(), model.output_nodes())
+        }, &[
+            (&[0,0], &[1,1,0]),
+            (&[1,0], &[0,1,0]),
+            (&[0,1], &[0,1,0]),
+            (&[1,1], &[0,1,0]),
+        ]);
+    }
+    
+    #[test]
+    fn test_latch() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let model = read_blif("
+.model shift
+.inputs clk d
+.outputs q late
+.latch d q re clk 0
+.latch q late fe clk 0
+.end
+", &mut creator).unwrap();
+        let clock = Pin::new(&mut creator);
+        let data = Pin::new(&mut creator);
+        creator.wire(clock.node, model.node("clk").unwrap());
+        creator.wire(data.node, model.node("d").unwrap());
+        c.absorb(creator);
+        let (q, late) = (model.node("q").unwrap(), model.node("late").unwrap());
+        
+        clock.node.write(LineState::Low, &mut c);
+        data.node.write(LineState::High, &mut c);
+        clock.node.write_later(LineState::High, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!(q.read(&c), LineState::High);
+        
+        clock.node.write_later(LineState::Low, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!(late.read(&c), LineState::High);
+        
+        data.node.write(LineState::Low, &mut c);
+        clock.node.write_later(LineState::High, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!((q.read(&c), late.read(&c)), (LineState::Low, LineState::High));
+    }
+    
+    #[test]
+    fn test_round_trip() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let adder = Adder::new(&mut creator);
+        let blif = write_blif("adder", &creator,
+            &[("operands", &[adder.a, adder.b][]), ("carry_in", &[adder.carry_in][])],
+            &[("sum", &[adder.sum][]), ("carry_out", &[adder.carry_out][])]).unwrap();
+        assert_eq!(blif.lines().filter(|line| { line.starts_with(".names") }).count(), creator.elements().len());
+        
+        check_truth_table(|creator: &mut NodeCreator| {
+            let model = read_blif(&blif[], creator).unwrap();
+            assert_eq!(model.inputs, vec!["operands[0]".to_string(), "operands[1]".to_string(), "carry_in".to_string()]);
+            (model.input_nodes(), model.output_nodes())
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,1,0], &[0,1]),
+            (&[1,0,1], &[0,1]),
+            (&[0,0,1], &[1,0]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_errors() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        assert_eq!(read_blif(".model m\n.inputs a\n.names a y\n10 1\n", &mut creator).err(),
+            Some("line 4: expected a cover row for 1 inputs".to_string()));
+        assert_eq!(read_blif(".model m\n.subckt adder a=x\n", &mut creator).err(),
+            Some("line 2: .subckt is not supported".to_string()));
+        assert_eq!(read_blif(".model m\n.inputs a\n.outputs y\n.names a y\n1 1\n0 0\n", &mut creator).err(),
+            Some("line 4: a cover must list only 1s or only 0s".to_string()));
+        
+        // The failed build left no model open, so the m before this one is its sibling
+        let model = read_blif(".model m\n.inputs a\n.outputs y\n.names a y\n1 1\n", &mut creator).unwrap();
+        let a = *model.signals.get("a").unwrap();
+        assert!(creator.node_names().iter().any(|&(node, ref name)| { node == a && &name[] == "m1.a" }));
+    }
+}
@@ -0,0 +1,215 @@
+use sim::{NodeIndex, NodeCreator, NodeCollection, Element, PropogationDelay, LineState};
+
+use arena::Arena;
+
+use std::u32;
+
+#[derive(Debug, Copy, Clone)]
+pub struct ClockParams {
+    pub period: u64,
+    pub high_ticks: u64, // How much of each period the clock spends high
+    pub phase: u64, // The clock is low until this tick, then starts its first high part
+    pub stop: Option<u64>, // No edges happen at or after this tick
+}
+
+impl ClockParams {
+    pub fn square(period: u64) -> ClockParams {
+        ClockParams {
+            period: period,
+            high_ticks: period/2,
+            phase: 0,
+            stop: None,
+        }
+    }
+    
+    fn level_at(&self, tick: u64) -> bool {
+        let tick = match self.stop {
+            Some(stop) if tick >= stop => {
+                if stop == 0 { return false; }
+                stop - 1
+            },
+            _ => tick
+        };
+        
+        if tick < self.phase {
+            return false;
+        }
+        (tick - self.phase) % self.period < self.high_ticks
+    }
+    
+    fn next_edge_after(&self, tick: u64) -> Option<u64> {
+        let edge = if self.high_ticks == 0 {
+            return None;
+        } else if tick < self.phase {
+            self.phase
+        } else if self.high_ticks >= self.period {
+            return None; // stuck high after the first edge
+        } else {
+            let offset = (tick - self.phase) % self.period;
+            let period_start = tick - offset;
+            if offset < self.high_ticks {
+                period_start + self.high_ticks
+            } else {
+                period_start + self.period
+            }
+        };
+        
+        match self.stop {
+            Some(stop) if edge >= stop => None,
+            _ => Some(edge)
+        }
+    }
+}
+
+fn line_state(high: bool) -> LineState {
+    if high { LineState::High } else { LineState::Low }
+}
+
+pub struct ClockElem {
+    pub node: NodeIndex,
+    pub params: ClockParams,
+}
+
+impl ClockElem {
+    pub fn new(params: ClockParams, c: &mut NodeCreator) -> ClockElem {
+        assert!(params.period > 0);
+        // Edges are scheduled as delays from now, which are never further off than the phase or a period
+        assert!(params.period <= u32::MAX as u64 && params.phase <= u32::MAX as u64, "clock period and phase must fit in a delay");
+        c.begin_component("Clock");
+        let elem = ClockElem {
+            node: c.new_named_node("node"),
+            params: params,
+        };
+        c.end_component();
+        elem
+    }
+}
+
+impl Element for ClockElem {
+    // The clock keeps no state of its own: whatever it last wrote tells it whether
+    // the next edge has already been scheduled.
+    fn step(&self, c: &mut NodeCollection) {
+        let now = c.current_tick;
+        let level = line_state(self.params.level_at(now));
+        let written = self.node.last_written(c);
+        
+        if written == LineState::Floating {
+            self.node.write(level, c);
+            return;
+        }
+        
+        if written != level {
+            return; // The next edge is already on its way.
+        }
+        
+        if let Some(edge) = self.params.next_edge_after(now) {
+            let state = line_state(self.params.level_at(edge));
+            assert!(edge - now <= u32::MAX as u64);
+            self.node.write_later(state, PropogationDelay((edge - now) as u32), c);
+        }
+    }
+    
+    fn get_nodes(&self) -> Vec<NodeIndex> {
+        let mut v = Vec::new();
+        v.push(self.node);
+        v
+    }
+    
+    fn kind(&self) -> &'static str {
+        "Clock"
+    }
+    
+    fn params(&self) -> Vec<u64> {
+        let mut params = vec![self.params.period, self.params.high_ticks, self.params.phase];
+        if let Some(stop) = self.params.stop {
+            params.push(stop);
+        }
+        params
+    }
+}
+
+pub struct Clock {
+    pub node: NodeIndex,
+}
+
+impl Clock {
+    pub fn new(params: ClockParams, creator: &mut NodeCreator) -> Clock {
+        let elem = creator.arena.alloc(|| { ClockElem::new(params, creator) });
+        creator.add_element(elem);
+        Clock {
+            node: elem.node
+        }
+    }
+}
+
+/// Several copies of one clock, with their phases spread evenly across the period.
+/// Giving each a high_ticks of period/phase_count makes them non-overlapping.
+pub struct MultiPhaseClock {
+    pub outputs: Vec<NodeIndex>,
+}
+
+impl MultiPhaseClock {
+    pub fn new(params: ClockParams, phase_count: usize, creator: &mut NodeCreator) -> MultiPhaseClock {
+        assert!(phase_count > 0);
+        
+        creator.begin_component("MultiPhaseClock");
+        let outputs = range(0, phase_count).map(|phase_index| {
+            let mut phase_params = params;
+            phase_params.phase = params.phase + (phase_index as u64)*params.period/(phase_count as u64);
+            Clock::new(phase_params, creator).node
+        }).collect();
+        creator.end_component();
+        
+        MultiPhaseClock {
+            outputs: outputs
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    
+    use sim::{NodeCollection, NodeCreator, LineState};
+    use super::{ClockParams, MultiPhaseClock};
+    
+    #[test]
+    fn test_multi_phase_clock() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let clocks = MultiPhaseClock::new(ClockParams{
+            period: 300,
+            high_ticks: 100,
+            phase: 50,
+            stop: Some(700),
+        }, 3, &mut creator);
+        c.absorb(creator);
+        
+        let edges = Rc::new(RefCell::new(Vec::new()));
+        for (index, output) in clocks.outputs.iter().enumerate() {
+            let recorder = edges.clone();
+            c.watch(*output, move |tick, _, new| {
+                recorder.borrow_mut().push((tick, index, new));
+            });
+        }
+        
+        while c.play() {}
+        
+        assert_eq!(&edges.borrow()[], &[
+            (50, 0, LineState::High),
+            (150, 1, LineState::High),
+            (150, 0, LineState::Low),
+            (250, 2, LineState::High),
+            (250, 1, LineState::Low),
+            (350, 0, LineState::High),
+            (350, 2, LineState::Low),
+            (450, 1, LineState::High),
+            (450, 0, LineState::Low),
+            (550, 2, LineState::High),
+            (550, 1, LineState::Low),
+            (650, 0, LineState::High),
+            (650, 2, LineState::Low),
+        ][]);
+    }
+}
@@ -0,0 +1,144 @@
+use sim::NodeIndex;
+
+/// Anything built with named ports, so that `component!` can check every port of its parts
+/// got connected. The gates and every builder declared with `component!` implement it;
+/// builders with buses for ports, like Mux or Register, do not, so they cannot be parts.
+pub trait Component {
+    fn ports(&self) -> Vec<(&'static str, NodeIndex)>;
+}
+
+/// The first port of `parts` that is not among the `connected` nodes, as "part.port".
+pub fn unconnected_port(parts: &[(&str, &Component)], connected: &[NodeIndex]) -> Option<String> {
+    for &(part_name, part) in parts.iter() {
+        for (port, node) in part.ports().into_iter() {
+            if !connected.contains(&node) {
+                return Some(format!("{}.{}", part_name, port));
+            }
+        }
+    }
+    None
+}
+
+/// Declares a component built out of other components, generating its struct, with a public
+/// field per port, and its `new(creator)`:
+///
+/// ```text
+/// component! {
+///     pub struct AndGate {
+///         parts { nander: NandGate, notter: NandGate }
+///         wires { nander.output -- notter.a, nander.output -- notter.b }
+///         ports { a: nander.a, b: nander.b, output: notter.output }
+///     }
+/// }
+/// ```
+///
+/// The parts are built in order inside a component named after the struct, then wired.
+/// Every port of every part has to be wired, exposed as a port or listed as unused; `new`
+/// panics naming the first that is not. Parts or ports that do not exist, and ports exposed
+/// twice, fail to compile.
+macro_rules! component {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            parts { $($part:ident: $kind:ident),* $(,)* }
+            wires { $($from:ident.$from_port:ident -- $to:ident.$to_port:ident),* $(,)* }
+            ports { $($port:ident: $port_part:ident.$port_node:ident),* $(,)* }
+            $(unused { $($unused_part:ident.$unused_port:ident),* $(,)* })*
+        }
+    ) => {
+        $(#[$attr])*
+        pub struct $name {
+            $(pub $port: ::sim::NodeIndex,)*
+        }
+        
+        impl $name {
+            pub fn new(creator: &mut ::sim::NodeCreator) -> $name {
+                creator.begin_component(stringify!($name));
+                $(let $part = $kind::new(creator);)*
+                
+                $(creator.wire($from.$from_port, $to.$to_port);)*
+                
+                creator.end_component();
+                let parts : &[(&str, &::component::Component)] = &[$((stringify!($part), &$part as &::component::Component)),*];
+                let connected = [$($from.$from_port, $to.$to_port,)* $($port_part.$port_node,)* $($($unused_part.$unused_port,)*)*];
+                if let Some(port) = ::component::unconnected_port(parts, &connected[]) {
+                    panic!("{}: {} is not connected", stringify!($name), port);
+                }
+                $name {
+                    $($port: $port_part.$port_node,)*
+                }
+            }
+        }
+        
+        impl ::component::Component for $name {
+            fn ports(&self) -> Vec<(&'static str, ::sim::NodeIndex)> {
+                vec![$((stringify!($port), self.$port)),*]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator};
+    use logic_gates::{NandGate, NotGate, OrGate};
+    use adder::HalfAdder;
+    use truth_table::check_truth_table;
+    use super::{Component, unconnected_port};
+    
+    component! {
+        pub struct NorGate {
+            parts { or: OrGate, not: NotGate }
+            wires { or.output -- not.input }
+            ports { a: or.a, b: or.b, output: not.output }
+        }
+    }
+    
+    component! {
+        pub struct FullAdder {
+            parts { first: HalfAdder, second: HalfAdder, either: OrGate }
+            wires { first.sum -- second.a, first.carry -- either.a, second.carry -- either.b }
+            ports { a: first.a, b: first.b, carry_in: second.b, sum: second.sum, carry_out: either.output }
+        }
+    }
+    
+    #[test]
+    fn test_component() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let nor = NorGate::new(creator);
+            assert_eq!(nor.ports().iter().map(|&(name, _)| { name }).collect::<Vec<&str>>(), vec!["a", "b", "output"]);
+            (vec![nor.a, nor.b], vec![nor.output])
+        }, &[
+            (&[0,0], &[1]),
+            (&[1,0], &[0]),
+            (&[0,1], &[0]),
+            (&[1,1], &[0]),
+        ]);
+    }
+    
+    #[test]
+    fn test_library_parts() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let adder = FullAdder::new(creator);
+            (vec![adder.a, adder.b, adder.carry_in], vec![adder.sum, adder.carry_out])
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,0,0], &[1,0]),
+            (&[1,1,0], &[0,1]),
+            (&[1,0,1], &[0,1]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_unconnected_port() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let first = NandGate::new(&mut creator);
+        let second = NandGate::new(&mut creator);
+        let parts : &[(&str, &Component)] = &[("first", &first as &Component), ("second", &second as &Component)];
+        
+        assert_eq!(unconnected_port(parts, &[first.a, first.b, first.output, second.a, second.b, second.output]), None);
+        assert_eq!(unconnected_port(parts, &[first.a, first.b, first.output, second.a, second.output]), Some("second.b".to_string()));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use sim::{NodeCollection, NodeIndex};
+
+/// Collects which nodes have been seen both Low and High, across any number of
+/// simulations. Nodes are matched up by name, so every simulation should build the
+/// same circuit the same way.
+pub struct Coverage {
+    seen: HashMap<String, (bool, bool)>,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage {
+            seen: HashMap::new(),
+        }
+    }
+    
+    pub fn record(&mut self, c: &NodeCollection) {
+        for index in range(0, c.node_count()) {
+            let node = NodeIndex(index);
+            let (seen_low, seen_high) = c.node_seen_states(node);
+            let name = c.node_name(node);
+            
+            let (was_low, was_high) = self.seen.get(&name).map(|seen| { *seen }).unwrap_or((false, false));
+            self.seen.insert(name, (was_low || seen_low, was_high || seen_high));
+        }
+    }
+    
+    /// Names of the nodes not yet seen both Low and High, in order.
+    pub fn untoggled(&self) -> Vec<String> {
+        let mut names : Vec<String> = self.seen.iter().filter(|&(_, &(seen_low, seen_high))| {
+            !(seen_low && seen_high)
+        }).map(|(name, _)| { name.clone() }).collect();
+        names.sort();
+        names
+    }
+    
+    /// The fraction of nodes that have been seen both Low and High, or 1 if no nodes have
+    /// been seen, since none were left untoggled.
+    pub fn fraction_toggled(&self) -> f64 {
+        if self.seen.is_empty() {
+            return 1.0;
+        }
+        let untoggled = self.untoggled().len();
+        ((self.seen.len() - untoggled) as f64) / (self.seen.len() as f64)
+    }
+    
+    /// Lists the untoggled nodes, grouped by the component `depth` levels down the
+    /// hierarchy that they belong to.
+    pub fn report(&self, depth: usize) -> String {
+        let mut report = format!("{:.1}% of {} nodes toggled\n", 100.0*self.fraction_toggled(), self.seen.len());
+        
+        let mut current_group = None;
+        for name in self.untoggled().into_iter() {
+            let group = component_prefix(&name[], depth);
+            if current_group.as_ref() != Some(&group) {
+                report.push_str(&format!("{}:\n", group)[]);
+                current_group = Some(group);
+            }
+            report.push_str(&format!("    {}\n", name)[]);
+        }
+        report
+    }
+}
+
+fn component_prefix(name: &str, depth: usize) -> String {
+    let component_path = match name.rfind('.') {
+        Some(port_start) => &name[..port_start],
+        None => name,
+    };
+    let segments : Vec<&str> = component_path.split('/').take(depth).collect();
+    segments.connect("/")
+}
+
+#[cfg(test)]
+mod test {
+    use truth_table::check_truth_table_with_coverage;
+    use sim::NodeCreator;
+    use logic_gates::AndGate;
+    use super::Coverage;
+    
+    #[test]
+    fn test_untoggled_input() {
+        let mut coverage = Coverage::new();
+        assert_eq!(coverage.fraction_toggled(), 1.0);
+        
+        // b never goes low
+        check_truth_table_with_coverage(&mut coverage, |creator: &mut NodeCreator| {
+            let and = AndGate::new(creator);
+            ([and.a, and.b].to_vec(), [and.output].to_vec())
+        }, &[
+            (&[1, 1], &[1]),
+            (&[0, 1], &[0]),
+        ]);
+        
+        let untoggled = coverage.untoggled();
+        assert!(untoggled.contains(&"AndGate0/Nand0.b".to_string()));
+        assert!(!untoggled.contains(&"AndGate0/Nand0.a".to_string()));
+        assert!(!untoggled.contains(&"AndGate0/Nand1.output".to_string()));
+        
+        // Once it has, everything in the gate toggles
+        check_truth_table_with_coverage(&mut coverage, |creator: &mut NodeCreator| {
+            let and = AndGate::new(creator);
+            ([and.a, and.b].to_vec(), [and.output].to_vec())
+        }, &[
+            (&[1, 0], &[0]),
+        ]);
+        
+        assert!(coverage.untoggled().iter().all(|name| { !name.starts_with("AndGate0/") }));
+    }
+}
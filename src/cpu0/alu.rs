@@ -1,4 +1,4 @@
-use super::super::sim::{NodeIndex, NodeCreator, STANDARD_DELAY};
+use super::super::sim::{NodeIndex, NodeCreator};
 use super::super::adder::RippleCarryAdder;
 use super::super::logic_gates::{AndGateVec, XorGateVec};
 use super::super::rom::Rom;
@@ -44,6 +44,7 @@ struct Alu {
 ///            Output
 impl Alu {
     pub fn new(params: &Params, creator: &mut NodeCreator) -> Alu {
+        creator.begin_component("Alu");
         let control_rom = Rom::new(&[
             // nonzro invert carry  and
             &[ false, false, false, true  ], // zero
@@ -66,38 +67,39 @@ impl Alu {
         
         // Possibly mask B to 0
         let masked_b = AndGateVec::new(params.word_bits, creator);
-        creator.link_one_to_many(keep_nonzero, &masked_b.b[], STANDARD_DELAY);
+        creator.wire_one_to_many(keep_nonzero, &masked_b.b[]);
         let b_raw_input = &masked_b.a;
         
         // Possibly invert (the masked) B
         let prepped_b_producer = XorGateVec::new(params.word_bits, creator);
-        creator.multilink(&masked_b.output[], &prepped_b_producer.a[], STANDARD_DELAY);
-        creator.link_one_to_many(do_invert, &prepped_b_producer.b[], STANDARD_DELAY);
+        creator.multiwire(&masked_b.output[], &prepped_b_producer.a[]);
+        creator.wire_one_to_many(do_invert, &prepped_b_producer.b[]);
         
         // Add the tweaked B with A
         let adder = RippleCarryAdder::new(creator, params.word_bits);
-        creator.multilink(&prepped_b_producer.output[], &adder.b[], STANDARD_DELAY);
+        creator.multiwire(&prepped_b_producer.output[], &adder.b[]);
         let a_raw_input = &adder.a;
-        creator.link(adder.carry_in, carry_in_set, STANDARD_DELAY);
+        creator.wire(adder.carry_in, carry_in_set);
         
         
         //
         // Build the ander-using branch
         //
         let ander = AndGateVec::new(params.word_bits, creator);
-        creator.multilink(&a_raw_input[], &ander.a[], STANDARD_DELAY);
-        creator.multilink(&masked_b.output[], &ander.b[], STANDARD_DELAY);
+        creator.multiwire(&a_raw_input[], &ander.a[]);
+        creator.multiwire(&masked_b.output[], &ander.b[]);
         
         
         //
         // Build the adder-vs-ander chooser
         //
         let chooser = Mux::new(params.word_bits, creator);
-        creator.multilink(&adder.sum[], &chooser.a[], STANDARD_DELAY);
-        creator.multilink(&ander.output[], &chooser.b[], STANDARD_DELAY);
-        creator.link(select_and, chooser.select, STANDARD_DELAY);
+        creator.multiwire(&adder.sum[], &chooser.a[]);
+        creator.multiwire(&ander.output[], &chooser.b[]);
+        creator.wire(select_and, chooser.select);
         
         
+        creator.end_component();
         Alu {
             a: a_raw_input.clone(),
             b: b_raw_input.clone(),
@@ -1,4 +1,4 @@
-use super::super::sim::{NodeIndex, NodeCreator, STANDARD_DELAY};
+use super::super::sim::{NodeIndex, NodeCreator};
 use super::super::mux::{MuxN};
 use super::super::demux::{DemuxN};
 use super::super::storage::Register;
@@ -14,26 +14,28 @@ struct RegisterBank {
 
 impl RegisterBank {
     fn new(params: &Params, creator: &mut NodeCreator) -> RegisterBank {
+        creator.begin_component("RegisterBank");
         let register_count = 1 << params.log_register_count;
         let registers: Vec<Register> = range(0, register_count).map(|_| { Register::new(creator, params.word_bits) }).collect();
         
         // Wire all inputs together
         for register in (&registers[1..]).iter() {
-            creator.multilink(&registers[0].inputs[], &register.inputs[], STANDARD_DELAY);
+            creator.multiwire(&registers[0].inputs[], &register.inputs[]);
         }
         
         let output_chooser = MuxN::new(params.word_bits, register_count, creator);
         for (register, output_chooser_source) in registers.iter().zip(output_chooser.inputs.iter()) {
-            creator.multilink(&register.outputs[], &output_chooser_source[], STANDARD_DELAY);
+            creator.multiwire(&register.outputs[], &output_chooser_source[]);
         }
         
         let demux = DemuxN::new(1, register_count, creator);
         assert_eq!(demux.outputs.len(), registers.len());
         for (register, demux_output) in registers.iter().zip(demux.outputs.iter()) {
             assert_eq!(demux_output.len(), 1);
-            creator.link(demux_output[0], register.clock, STANDARD_DELAY);
+            creator.wire(demux_output[0], register.clock);
         }
         
+        creator.end_component();
         RegisterBank {
             input: registers[0].inputs.clone(),
             write_selector: demux.select,
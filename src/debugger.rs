@@ -0,0 +1,331 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use sim::{NodeCollection, NodeIndex, LineState, read_bus};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    Node(NodeIndex, LineState), // When the node resolves to this state
+    Bus(Vec<NodeIndex>, u64), // When the bus, least significant bit first, reads this value
+    Tick(u64), // When the simulation reaches this tick
+}
+
+impl Breakpoint {
+    fn holds(&self, c: &NodeCollection) -> bool {
+        match *self {
+            Breakpoint::Node(node, state) => node.read(c) == state,
+            Breakpoint::Bus(ref nodes, value) => read_bus(&nodes[], c) == Some(value),
+            Breakpoint::Tick(tick) => c.current_tick >= tick,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StopReason {
+    Breakpoint(usize),
+    Quiescent, // No events left to play
+}
+
+/// Steps a collection one event or one tick at a time, stopping at breakpoints.
+/// A breakpoint fires when its condition goes from false to true, not while it stays true.
+/// The collection records its history while being debugged, so it can also be stepped backwards.
+pub struct Debugger<'c, 'a: 'c> {
+    pub c: &'c mut NodeCollection<'a>,
+    breakpoints: Vec<Option<Breakpoint>>, // Deleted breakpoints leave a hole, so ids stay put
+}
+
+impl<'c, 'a> Debugger<'c, 'a> {
+    pub fn new(c: &'c mut NodeCollection<'a>) -> Debugger<'c, 'a> {
+        c.record_history(true);
+        Debugger {
+            c: c,
+            breakpoints: Vec::new(),
+        }
+    }
+    
+    /// Returns an id for `delete_breakpoint`.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(Some(breakpoint));
+        self.breakpoints.len() - 1
+    }
+    
+    pub fn delete_breakpoint(&mut self, id: usize) {
+        self.breakpoints[id] = None;
+    }
+    
+    pub fn breakpoints(&self) -> Vec<(usize, Breakpoint)> {
+        self.breakpoints.iter().enumerate().filter_map(|(id, breakpoint)| {
+            breakpoint.as_ref().map(|breakpoint| { (id, breakpoint.clone()) })
+        }).collect()
+    }
+    
+    fn holding_breakpoints(&self) -> Vec<bool> {
+        self.breakpoints.iter().map(|breakpoint| {
+            match *breakpoint {
+                Some(ref breakpoint) => breakpoint.holds(self.c),
+                None => false,
+            }
+        }).collect()
+    }
+    
+    /// Plays one event. Returns false if there was none.
+    pub fn step_event(&mut self) -> bool {
+        self.c.play()
+    }
+    
+    /// Plays every event at the next tick that has any. Returns false if there were none.
+    pub fn step_tick(&mut self) -> bool {
+        match self.c.next_event_time() {
+            Some(tick) => {
+                self.c.play_until(tick);
+                true
+            },
+            None => false
+        }
+    }
+    
+    /// Undoes the most recently played event. Returns false if there is no history left.
+    pub fn step_back_event(&mut self) -> bool {
+        self.c.step_back()
+    }
+    
+    /// Undoes every event of the most recent tick that had any. Returns false if there is no history left.
+    pub fn step_back_tick(&mut self) -> bool {
+        match self.c.last_recorded_time() {
+            Some(tick) => {
+                while self.c.last_recorded_time() == Some(tick) {
+                    self.c.step_back();
+                }
+                true
+            },
+            None => false
+        }
+    }
+    
+    pub fn continue_running(&mut self) -> StopReason {
+        let mut held = self.holding_breakpoints();
+        loop {
+            let next_time = match self.c.next_event_time() {
+                Some(tick) => tick,
+                None => { return StopReason::Quiescent; }
+            };
+            
+            // A tick breakpoint between now and the next event stops the clock there.
+            let tick_stop = self.breakpoints.iter().filter_map(|breakpoint| {
+                match *breakpoint {
+                    Some(Breakpoint::Tick(tick)) if tick > self.c.current_tick && tick < next_time => Some(tick),
+                    _ => None
+                }
+            }).min();
+            
+            match tick_stop {
+                Some(tick) => { self.c.play_until(tick); },
+                None => { self.c.play(); },
+            }
+            
+            let holds = self.holding_breakpoints();
+            let fired = holds.iter().zip(held.iter()).position(|(now, before)| { *now && !*before });
+            if let Some(id) = fired {
+                return StopReason::Breakpoint(id);
+            }
+            held = holds;
+        }
+    }
+    
+    pub fn describe_node(&self, node: NodeIndex) -> String {
+        let mut description = format!("{} = {:?}", self.c.node_name(node), node.read(self.c));
+        if let Some(forced) = self.c.forced_state(node) {
+            description.push_str(&format!(" (forced to {:?})", forced)[]);
+        }
+        description.push_str(&format!(", last wrote {:?}\n", node.last_written(self.c))[]);
+        
+        for &(forcer, state) in self.c.influences(node).iter() {
+            description.push_str(&format!("  influenced by {}: {:?}\n", self.c.node_name(forcer), state)[]);
+        }
+        for event in self.c.pending_events().iter().filter(|event| { event.node == node }) {
+            description.push_str(&format!("  pending at t={}: {:?} from {}\n", event.time, event.new_state, self.c.node_name(event.forcer))[]);
+        }
+        description
+    }
+    
+    pub fn describe_pending(&self, limit: usize) -> String {
+        let mut description = String::new();
+        for event in self.c.pending_events().iter().take(limit) {
+            description.push_str(&format!("t={}: {} <- {:?} from {}\n", event.time, self.c.node_name(event.node), event.new_state, self.c.node_name(event.forcer))[]);
+        }
+        description
+    }
+}
+
+fn parse_state(word: &str) -> Option<LineState> {
+    match word {
+        "0" | "low" => Some(LineState::Low),
+        "1" | "high" => Some(LineState::High),
+        "z" | "floating" => Some(LineState::Floating),
+        "x" | "conflict" => Some(LineState::Conflict),
+        _ => None,
+    }
+}
+
+const HELP: &'static str = "commands:
+  break <node> <0|1|z|x>         stop when a node resolves to a state
+  break-bus <value> <node>...    stop when a bus (lsb first) reads a value
+  break-tick <tick>              stop when the simulation reaches a tick
+  delete <id>                    delete a breakpoint
+  step | s                       play one event
+  tick | t                       play every event at the next tick
+  continue | c                   run until a breakpoint or until nothing is left
+  back | b                       undo the last played event
+  back-tick                      undo every event of the last tick
+  back-to <tick>                 undo everything after a tick
+  print | p <node>               show a node's state, influences and pending events
+  pending [count]                show the next events to be played
+  info                           show the current tick and breakpoints
+  quit | q
+";
+
+fn run_command(debugger: &mut Debugger, words: &[&str]) -> Result<String, String> {
+    let find = |debugger: &Debugger, name: &str| -> Result<NodeIndex, String> {
+        debugger.c.find_node(name).ok_or(format!("no node named {}", name))
+    };
+    
+    match words {
+        ["break", name, state] => {
+            let node = try!(find(debugger, name));
+            let state = try!(parse_state(state).ok_or(format!("unknown state {}", state)));
+            Ok(format!("breakpoint {}", debugger.add_breakpoint(Breakpoint::Node(node, state))))
+        },
+        ["break-bus", value, names..] => {
+            let value = try!(value.parse::<u64>().map_err(|_| { format!("bad value {}", value) }));
+            let mut nodes = Vec::new();
+            for name in names.iter() {
+                nodes.push(try!(find(debugger, *name)));
+            }
+            Ok(format!("breakpoint {}", debugger.add_breakpoint(Breakpoint::Bus(nodes, value))))
+        },
+        ["break-tick", tick] => {
+            let tick = try!(tick.parse::<u64>().map_err(|_| { format!("bad tick {}", tick) }));
+            Ok(format!("breakpoint {}", debugger.add_breakpoint(Breakpoint::Tick(tick))))
+        },
+        ["delete", id] => {
+            let id = try!(id.parse::<usize>().map_err(|_| { format!("bad breakpoint id {}", id) }));
+            if id >= debugger.breakpoints.len() {
+                return Err(format!("no breakpoint {}", id));
+            }
+            debugger.delete_breakpoint(id);
+            Ok(String::new())
+        },
+        ["step"] | ["s"] => {
+            let played = debugger.step_event();
+            Ok(if played { format!("t={}", debugger.c.current_tick) } else { "nothing left to play".to_string() })
+        },
+        ["tick"] | ["t"] => {
+            let played = debugger.step_tick();
+            Ok(if played { format!("t={}", debugger.c.current_tick) } else { "nothing left to play".to_string() })
+        },
+        ["back"] | ["b"] => {
+            let undone = debugger.step_back_event();
+            Ok(if undone { format!("t={}", debugger.c.current_tick) } else { "no history left".to_string() })
+        },
+        ["back-tick"] => {
+            let undone = debugger.step_back_tick();
+            Ok(if undone { format!("t={}", debugger.c.current_tick) } else { "no history left".to_string() })
+        },
+        ["back-to", tick] => {
+            let tick = try!(tick.parse::<u64>().map_err(|_| { format!("bad tick {}", tick) }));
+            debugger.c.step_back_to(tick);
+            Ok(format!("t={}", debugger.c.current_tick))
+        },
+        ["continue"] | ["c"] => {
+            let reason = debugger.continue_running();
+            Ok(format!("t={}: {:?}", debugger.c.current_tick, reason))
+        },
+        ["print", name] | ["p", name] => {
+            let node = try!(find(debugger, name));
+            Ok(debugger.describe_node(node))
+        },
+        ["pending"] => Ok(debugger.describe_pending(20)),
+        ["pending", count] => {
+            let count = try!(count.parse::<usize>().map_err(|_| { format!("bad count {}", count) }));
+            Ok(debugger.describe_pending(count))
+        },
+        ["info"] => {
+            let mut info = format!("t={}, {} events pending\n", debugger.c.current_tick, debugger.c.pending_events().len());
+            for &(id, ref breakpoint) in debugger.breakpoints().iter() {
+                info.push_str(&format!("  {}: {:?}\n", id, breakpoint)[]);
+            }
+            Ok(info)
+        },
+        _ => Err(HELP.to_string()),
+    }
+}
+
+/// Reads debugger commands from stdin until it closes or the user quits.
+pub fn repl(c: &mut NodeCollection) {
+    let mut debugger = Debugger::new(c);
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    
+    print!("{}", HELP);
+    loop {
+        print!("t={}> ", debugger.c.current_tick);
+        stdout.flush().unwrap();
+        
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        let words : Vec<&str> = line.split(' ').map(|word| { word.trim() }).filter(|word| { !word.is_empty() }).collect();
+        
+        match &words[] {
+            [] => {},
+            ["quit"] | ["q"] => { break; },
+            words => match run_command(&mut debugger, words) {
+                Ok(output) => { println!("{}", output.trim_right()); },
+                Err(message) => { println!("{}", message.trim_right()); },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay};
+    use logic_gates::NotGate;
+    use pin::Pin;
+    use super::{Debugger, Breakpoint, StopReason};
+    
+    #[test]
+    fn test_breakpoints() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let not = NotGate::new(&mut creator);
+        let input = Pin::new(&mut creator);
+        creator.wire(input.node, not.input);
+        c.absorb(creator);
+        
+        input.node.write(LineState::Low, &mut c);
+        input.node.write_later(LineState::High, PropogationDelay(1000), &mut c);
+        
+        let mut debugger = Debugger::new(&mut c);
+        let output_low = debugger.add_breakpoint(Breakpoint::Node(not.output, LineState::Low));
+        let at_500 = debugger.add_breakpoint(Breakpoint::Tick(500));
+        
+        assert_eq!(debugger.continue_running(), StopReason::Breakpoint(at_500));
+        assert_eq!(debugger.c.current_tick, 500);
+        assert_eq!(not.output.read(debugger.c), LineState::High);
+        
+        assert_eq!(debugger.continue_running(), StopReason::Breakpoint(output_low));
+        assert!(debugger.c.current_tick > 1000);
+        assert_eq!(not.output.read(debugger.c), LineState::Low);
+        
+        assert_eq!(debugger.continue_running(), StopReason::Quiescent);
+        
+        // Walking back to before the input rose brings the output back up
+        while debugger.c.last_recorded_time().map(|tick| { tick >= 1000 }).unwrap_or(false) {
+            assert!(debugger.step_back_tick());
+        }
+        assert_eq!(not.output.read(debugger.c), LineState::High);
+        assert_eq!(debugger.continue_running(), StopReason::Breakpoint(output_low));
+    }
+}
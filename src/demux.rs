@@ -1,32 +1,20 @@
 use logic_gates::{AndGate, NotGate, AndGateVec};
-use sim::{NodeIndex, NodeCreator, STANDARD_DELAY};
+use sim::{NodeIndex, NodeCreator};
 
-/// Choose between two bits
-pub struct BitDemux {
-    pub input: NodeIndex,
-    pub select: NodeIndex,
-    pub output_a: NodeIndex,
-    pub output_b: NodeIndex,
-    pub enable: NodeIndex,
-}
-
-impl BitDemux {
-    pub fn new(creator: &mut NodeCreator) -> BitDemux {
-        let ander_a = AndGate::new(creator);
-        let not_select = NotGate::new(creator);
-        let ander_b = AndGate::new(creator);
-        let enabler_a = AndGate::new(creator);
-        let enabler_b = AndGate::new(creator);
-        
-        creator.link(not_select.output, ander_a.a, STANDARD_DELAY);
-        creator.link(not_select.input, ander_b.a, STANDARD_DELAY);
-        creator.link(ander_a.b, ander_b.b, STANDARD_DELAY);
-        
-        creator.link(enabler_a.a, enabler_b.a, STANDARD_DELAY);
-        creator.link(enabler_a.b, ander_a.output, STANDARD_DELAY);
-        creator.link(enabler_b.b, ander_b.output, STANDARD_DELAY);
-        
-        BitDemux {
+component! {
+    /// Choose between two bits
+    pub struct BitDemux {
+        parts { ander_a: AndGate, not_select: NotGate, ander_b: AndGate, enabler_a: AndGate, enabler_b: AndGate }
+        wires {
+            not_select.output -- ander_a.a,
+            not_select.input -- ander_b.a,
+            ander_a.b -- ander_b.b,
+            
+            enabler_a.a -- enabler_b.a,
+            enabler_a.b -- ander_a.output,
+            enabler_b.b -- ander_b.output,
+        }
+        ports {
             input: ander_a.b,
             select: not_select.input,
             output_a: enabler_a.output,
@@ -48,13 +36,15 @@ pub struct Demux {
 
 impl Demux {
     pub fn new(word_bits: usize, creator: &mut NodeCreator) -> Demux {
+        creator.begin_component("Demux");
         let bits : Vec<BitDemux> = range(0, word_bits).map(|_| { BitDemux::new(creator) } ).collect();
         
         for bit in (&bits[1..]).iter() {
-            creator.link(bits[0].select, bit.select, STANDARD_DELAY);
-            creator.link(bits[0].enable, bit.enable, STANDARD_DELAY);
+            creator.wire(bits[0].select, bit.select);
+            creator.wire(bits[0].enable, bit.enable);
         }
         
+        creator.end_component();
         Demux {
             input: bits.iter().map(|bit| { bit.input }).collect(),
             select: bits[0].select,
@@ -77,9 +67,10 @@ impl DemuxN {
     pub fn new(word_bits: usize, word_count: usize, creator: &mut NodeCreator) -> DemuxN {
         assert!(word_count>0);
         
-        if word_count == 1 {
+        creator.begin_component("DemuxN");
+        let demux = if word_count == 1 {
             let ands = AndGateVec::new(word_bits, creator);
-            creator.link_one_to_many(ands.b[0], &ands.b[], STANDARD_DELAY);
+            creator.wire_one_to_many(ands.b[0], &ands.b[]);
             DemuxN {
                 input: ands.a,
                 outputs: [ands.output].to_vec(),
@@ -96,7 +87,7 @@ impl DemuxN {
             let mut lower = DemuxN::new(word_bits, lower_size, creator);
             let mut upper = DemuxN::new(word_bits, word_count - lower_size, creator);
             
-            creator.multilink(&lower.input[], &upper.input[], STANDARD_DELAY);
+            creator.multiwire(&lower.input[], &upper.input[]);
             
             let mut outputs = lower.outputs;
             outputs.append(&mut upper.outputs);
@@ -108,14 +99,14 @@ impl DemuxN {
             let upper_select = lower_select_gen.input;
             let lower_select = lower_select_gen.output;
             let enable = lower_enabler.b;
-            creator.link(enable, upper_enabler.b, STANDARD_DELAY); // The enablers share one input -- the one enabling this whole thing
-            creator.link(lower_enabler.a, lower_select, STANDARD_DELAY);
-            creator.link(upper_enabler.a, upper_select, STANDARD_DELAY);
-            creator.link(lower_enabler.output, lower.enable, STANDARD_DELAY);
-            creator.link(upper_enabler.output, upper.enable, STANDARD_DELAY);
+            creator.wire(enable, upper_enabler.b); // The enablers share one input -- the one enabling this whole thing
+            creator.wire(lower_enabler.a, lower_select);
+            creator.wire(upper_enabler.a, upper_select);
+            creator.wire(lower_enabler.output, lower.enable);
+            creator.wire(upper_enabler.output, upper.enable);
             
             let mut select = lower.select.clone();
-            creator.multilink(&lower.select[..upper.select.len()], &upper.select[], STANDARD_DELAY);
+            creator.multiwire(&lower.select[..upper.select.len()], &upper.select[]);
             select.push(upper_select);
             
             
@@ -125,7 +116,9 @@ impl DemuxN {
                 outputs: outputs,
                 enable: enable,
             }
-        }
+        };
+        creator.end_component();
+        demux
     }
 }
 
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use sim::{NodeCreator, NodeCollection, NodeIndex, Element, PropogationDelay};
+
+// What a node of the circuit is drawn as: the element it belongs to, collapsed into
+// its component at the chosen depth, or for nodes no element owns, the node itself.
+struct Graph {
+    vertices: Vec<(String, String)>, // Id and label, in the order they were first seen
+    elements_in: HashMap<String, usize>,
+    edges: Vec<(String, String, Vec<String>)>, // Each pair of vertices once, with every delay between them
+}
+
+impl Graph {
+    fn add_vertex(&mut self, id: &str, label: String) {
+        if !self.vertices.iter().any(|&(ref existing, _)| { &existing[] == id }) {
+            self.vertices.push((id.to_string(), label));
+        }
+    }
+    
+    fn add_edge(&mut self, a: String, b: String, delay: String) {
+        if a == b {
+            return;
+        }
+        for &mut (ref from, ref to, ref mut delays) in self.edges.iter_mut() {
+            if (*from == a && *to == b) || (*from == b && *to == a) {
+                if !delays.contains(&delay) {
+                    delays.push(delay);
+                }
+                return;
+            }
+        }
+        self.edges.push((a, b, vec![delay]));
+    }
+}
+
+// The path of a node's component, and the port after it.
+fn split_name(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) => (&name[..dot], &name[dot + 1..]),
+        None => (name, ""),
+    }
+}
+
+// The first `depth` levels of a path, if it goes deeper than that.
+fn collapse(path: &str, depth: Option<usize>) -> Option<String> {
+    let depth = match depth {
+        Some(depth) => depth,
+        None => { return None; }
+    };
+    let levels : Vec<&str> = path.split('/').collect();
+    if levels.len() > depth {
+        Some(levels[..depth].connect("/"))
+    } else {
+        None
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace("\"", "\\\""))
+}
+
+fn build_dot<F>(elements: &[&Element], links: Vec<(NodeIndex, NodeIndex, String)>, node_name: F, depth: Option<usize>) -> String
+    where F: Fn(NodeIndex) -> String
+{
+    let mut graph = Graph { vertices: Vec::new(), elements_in: HashMap::new(), edges: Vec::new() };
+    let mut owners : HashMap<NodeIndex, String> = HashMap::new();
+    
+    for element in elements.iter() {
+        let nodes = element.get_nodes();
+        let name = nodes.first().map(|node| { node_name(*node) }).unwrap_or(String::new());
+        let path = split_name(&name[]).0.to_string();
+        let id = match collapse(&path[], depth) {
+            Some(component) => {
+                let count = graph.elements_in.get(&component).map(|count| { *count }).unwrap_or(0);
+                graph.elements_in.insert(component.clone(), count + 1);
+                graph.add_vertex(&component[], String::new());
+                component
+            },
+            None => {
+                let params : Vec<String> = element.params().iter().map(|param| { param.to_string() }).collect();
+                graph.add_vertex(&path[], format!("{}({})\\n{}", element.kind(), params.connect(", "), path));
+                path
+            }
+        };
+        for node in nodes.iter() {
+            owners.insert(*node, id.clone());
+        }
+    }
+    
+    for (a, b, delay) in links.into_iter() {
+        let mut ends = Vec::new();
+        for node in [a, b].iter() {
+            let id = match owners.get(node) {
+                Some(id) => id.clone(),
+                None => {
+                    let name = node_name(*node);
+                    match collapse(split_name(&name[]).0, depth) {
+                        Some(component) => {
+                            graph.add_vertex(&component[], String::new());
+                            component
+                        },
+                        None => {
+                            graph.add_vertex(&name[], name.clone());
+                            name
+                        }
+                    }
+                }
+            };
+            ends.push(id);
+        }
+        let b = ends.pop().unwrap();
+        let a = ends.pop().unwrap();
+        graph.add_edge(a, b, delay);
+    }
+    
+    let mut dot = "graph circuit {\n    node [shape=box];\n".to_string();
+    for &(ref id, ref label) in graph.vertices.iter() {
+        let label = if label.is_empty() {
+            // A collapsed component
+            let count = graph.elements_in.get(id).map(|count| { *count }).unwrap_or(0);
+            format!("{}\\n{} elements", id, count)
+        } else {
+            label.clone()
+        };
+        dot.push_str(&format!("    {} [label={}];\n", quote(&id[]), quote(&label[]))[]);
+    }
+    for &(ref a, ref b, ref delays) in graph.edges.iter() {
+        dot.push_str(&format!("    {} -- {} [label={}];\n", quote(&a[]), quote(&b[]), quote(&delays.connect(", ")[]))[]);
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Draws everything added to the creator as a Graphviz graph, with an element per vertex and
+/// a link per edge, labelled with its delay or "wire". With a depth, elements deeper in the
+/// hierarchy than that are drawn as the component containing them, so depth 1 shows an Adder
+/// as one box rather than its NANDs.
+pub fn write_dot(creator: &NodeCreator, depth: Option<usize>) -> String {
+    let mut names = HashMap::new();
+    for &(node, ref name) in creator.node_names().iter() {
+        names.insert(node, name.clone());
+    }
+    let links = creator.links().into_iter().map(|(a, b, delay)| {
+        (a, b, match delay {
+            Some(PropogationDelay(ticks)) => ticks.to_string(),
+            None => "wire".to_string(),
+        })
+    }).collect();
+    build_dot(creator.elements(), links, |node| {
+        names.get(&node).map(|name| { name.clone() }).unwrap_or(format!("#{}", node.0))
+    }, depth)
+}
+
+/// Like `write_dot`, for a collection, with the delays the wires ended up with.
+pub fn write_collection_dot(c: &NodeCollection, depth: Option<usize>) -> String {
+    let links = c.links().into_iter().map(|(a, b, PropogationDelay(ticks))| { (a, b, ticks.to_string()) }).collect();
+    build_dot(c.elements(), links, |node| { c.node_name(node) }, depth)
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, PropogationDelay};
+    use logic_gates::AndGate;
+    use adder::Adder;
+    use super::{write_dot, write_collection_dot};
+    
+    #[test]
+    fn test_elements() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let and = AndGate::new(&mut creator);
+        let other = AndGate::new(&mut creator);
+        creator.link(and.output, other.a, PropogationDelay(30));
+        assert_eq!(write_dot(&creator, None), "graph circuit {
+    node [shape=box];
+    \"AndGate0/Nand0\" [label=\"Nand(0)\\nAndGate0/Nand0\"];
+    \"AndGate0/Nand1\" [label=\"Nand(0)\\nAndGate0/Nand1\"];
+    \"AndGate1/Nand0\" [label=\"Nand(0)\\nAndGate1/Nand0\"];
+    \"AndGate1/Nand1\" [label=\"Nand(0)\\nAndGate1/Nand1\"];
+    \"AndGate0/Nand0\" -- \"AndGate0/Nand1\" [label=\"wire\"];
+    \"AndGate1/Nand0\" -- \"AndGate1/Nand1\" [label=\"wire\"];
+    \"AndGate0/Nand1\" -- \"AndGate1/Nand0\" [label=\"30\"];
+}
+");
+    }
+    
+    #[test]
+    fn test_collapse() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let low = Adder::new(&mut creator);
+        let high = Adder::new(&mut creator);
+        creator.wire(low.carry_out, high.carry_in);
+        let bare = creator.new_node();
+        creator.wire(bare, low.a);
+        let elements_per_adder = creator.elements().len() / 2;
+        
+        let dot = write_dot(&creator, Some(1));
+        assert_eq!(dot, format!("graph circuit {{
+    node [shape=box];
+    \"Adder0\" [label=\"Adder0\\n{0} elements\"];
+    \"Adder1\" [label=\"Adder1\\n{0} elements\"];
+    \"#{1}\" [label=\"#{1}\"];
+    \"Adder0\" -- \"Adder1\" [label=\"wire\"];
+    \"#{1}\" -- \"Adder0\" [label=\"wire\"];
+}}
+", elements_per_adder, bare.0));
+
+        c.absorb(creator);
+        let dot = write_collection_dot(&c, Some(1));
+        assert!(dot.contains("\"Adder0\" -- \"Adder1\" [label=\"100\"];"));
+    }
+}
@@ -0,0 +1,369 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use sim::{NodeCreator, NodeIndex, NodeCollection, LineState};
+use pin::Pin;
+use storage::DFlipFlop;
+
+pub struct FaultReport {
+    pub fault_count: usize,
+    pub undetected: Vec<(String, LineState)>, // Node names, and the state they were stuck at
+}
+
+impl FaultReport {
+    /// The fraction of faults detected, 1 if there were none to detect.
+    pub fn coverage(&self) -> f64 {
+        if self.fault_count == 0 {
+            return 1.0;
+        }
+        ((self.fault_count - self.undetected.len()) as f64) / (self.fault_count as f64)
+    }
+}
+
+/// Runs the cases against a freshly built circuit, with `fault` stuck from the start.
+/// Returns whether every output matched, and the name of the faulty node.
+fn run_with_stuck_node<F>(f: &F, cases: &[(&[u8],&[u8])], fault: Option<(NodeIndex, LineState)>) -> (bool, String)
+    where F: Fn(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
+{
+    let mut c = NodeCollection::new();
+    let mut creator = NodeCreator::new(&c);
+    
+    let (inputs, outputs) = (*f)(&mut creator);
+    let input_pins : Vec<Pin> = inputs.iter().map(|input| {
+        let p = Pin::new(&mut creator);
+        creator.wire(*input, p.node);
+        p
+    }).collect();
+    
+    c.absorb(creator);
+    
+    let fault_name = match fault {
+        Some((node, state)) => {
+            c.force(node, state);
+            c.node_name(node)
+        },
+        None => String::new(),
+    };
+    
+    for &(input_values, output_values) in cases.iter() {
+        assert!(input_values.len() == input_pins.len());
+        assert!(output_values.len() == outputs.len());
+        for (input_pin, input_value) in input_pins.iter().zip(input_values.iter()) {
+            input_pin.node.write(if *input_value==1 { LineState::High } else { LineState::Low }, &mut c);
+        }
+        while c.play() {}
+        
+        for (output, expected) in outputs.iter().zip(output_values.iter()) {
+            let expected = if *expected==1 { LineState::High } else { LineState::Low };
+            if output.read(&c) != expected {
+                return (false, fault_name);
+            }
+        }
+    }
+    
+    (true, fault_name)
+}
+
+/// Tries every single stuck-at-0 and stuck-at-1 fault on the nodes of the circuit `f`
+/// builds, and reports which of them the truth table cases fail to notice.
+pub fn stuck_at_coverage<F>(f: F, cases: &[(&[u8],&[u8])]) -> FaultReport
+    where F: Fn(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
+{
+    let circuit_node_count = {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        f(&mut creator);
+        let count = creator.node_count();
+        count
+    };
+    
+    let (passes, _) = run_with_stuck_node(&f, cases, None);
+    assert!(passes, "The circuit fails its cases even without any faults");
+    
+    let mut fault_count = 0;
+    let mut undetected = Vec::new();
+    for index in range(0, circuit_node_count) {
+        for state in [LineState::Low, LineState::High].iter() {
+            fault_count += 1;
+            let (passes, name) = run_with_stuck_node(&f, cases, Some((NodeIndex(index), *state)));
+            if passes {
+                undetected.push((name, *state));
+            }
+        }
+    }
+    
+    FaultReport {
+        fault_count: fault_count,
+        undetected: undetected,
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Upset {
+    Force(LineState),
+    Flip, // Force the opposite of whatever the node reads when the fault starts
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TransientFault {
+    pub node: NodeIndex,
+    pub upset: Upset,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl TransientFault {
+    /// Flips the bit a flip-flop holds by briefly overpowering its output. The duration
+    /// needs to be long enough for the flipped value to get around the output latch.
+    pub fn flip_flop_upset(flip_flop: &DFlipFlop, tick: u64, duration: u64) -> TransientFault {
+        TransientFault {
+            node: flip_flop.q,
+            upset: Upset::Flip,
+            start: tick,
+            end: tick + duration,
+        }
+    }
+}
+
+/// Plays `c` up to `until`, forcing each fault's node during its window. Where faults on
+/// one node overlap, the one that started last wins, and the node is released once they
+/// have all ended.
+pub fn play_with_faults(c: &mut NodeCollection, faults: &[TransientFault], until: u64) {
+    // (tick, fault index, whether the fault starts)
+    let mut actions : Vec<(u64, usize, bool)> = Vec::new();
+    for (index, fault) in faults.iter().enumerate() {
+        actions.push((fault.start, index, true));
+        actions.push((fault.end, index, false));
+    }
+    actions.sort_by(|a, b| { a.0.cmp(&b.0) });
+    
+    // The faults holding each node down, oldest first, with the state each forces.
+    let mut active : HashMap<NodeIndex, Vec<(usize, LineState)>> = HashMap::new();
+    for &(tick, index, starts) in actions.iter() {
+        if tick > until {
+            break;
+        }
+        c.play_until(tick);
+        
+        let fault = &faults[index];
+        if !starts {
+            let remaining = match active.get_mut(&fault.node) {
+                Some(holding) => {
+                    holding.retain(|&(holder, _)| { holder != index });
+                    holding.last().map(|&(_, state)| { state })
+                },
+                None => { continue; } // It never started, such as a flip of a node that was neither high nor low
+            };
+            match remaining {
+                Some(state) => {
+                    if c.forced_state(fault.node) != Some(state) {
+                        c.force(fault.node, state);
+                    }
+                },
+                None => {
+                    active.remove(&fault.node);
+                    c.release(fault.node);
+                },
+            }
+            continue;
+        }
+        
+        let state = match fault.upset {
+            Upset::Force(state) => state,
+            Upset::Flip => match fault.node.read(c) {
+                LineState::Low => LineState::High,
+                LineState::High => LineState::Low,
+                _ => { continue; } // Nothing to flip
+            }
+        };
+        if !active.contains_key(&fault.node) {
+            active.insert(fault.node, Vec::new());
+        }
+        active.get_mut(&fault.node).unwrap().push((index, state));
+        c.force(fault.node, state);
+    }
+    
+    c.play_until(until);
+}
+
+/// An output whose behavior differed from the fault-free run.
+#[derive(Debug)]
+pub struct Corruption {
+    pub output: usize,
+    pub name: String,
+    pub first_difference: u64,
+    pub wrong_at_end: bool,
+}
+
+fn record_outputs(c: &mut NodeCollection, outputs: &[NodeIndex]) -> Vec<Rc<RefCell<Vec<(u64, LineState)>>>> {
+    outputs.iter().map(|output| {
+        let trace = Rc::new(RefCell::new([(c.current_tick, output.read(c))].to_vec()));
+        let recorder = trace.clone();
+        c.watch(*output, move |tick, _, new| {
+            recorder.borrow_mut().push((tick, new));
+        });
+        trace
+    }).collect()
+}
+
+fn state_at(trace: &[(u64, LineState)], tick: u64) -> LineState {
+    let mut state = trace[0].1;
+    for &(change_tick, new_state) in trace.iter() {
+        if change_tick > tick { break; }
+        state = new_state;
+    }
+    state
+}
+
+/// Runs the experiment set up by `f` twice, once cleanly and once with the transient
+/// faults it asks for, and reports which outputs the faults corrupted.
+/// `f` builds and absorbs a circuit into the collection, schedules its stimulus, and
+/// returns the outputs to watch along with the faults to inject.
+pub fn upset_experiment<F>(f: F, until: u64) -> Vec<Corruption>
+    where F: Fn(&mut NodeCollection) -> (Vec<NodeIndex>, Vec<TransientFault>)
+{
+    let mut clean = NodeCollection::new();
+    let (outputs, _) = f(&mut clean);
+    let clean_traces = record_outputs(&mut clean, &outputs[]);
+    clean.play_until(until);
+    
+    let mut faulty = NodeCollection::new();
+    let (outputs, faults) = f(&mut faulty);
+    let faulty_traces = record_outputs(&mut faulty, &outputs[]);
+    play_with_faults(&mut faulty, &faults[], until);
+    
+    let mut corruptions = Vec::new();
+    for (index, (clean_trace, faulty_trace)) in clean_traces.iter().zip(faulty_traces.iter()).enumerate() {
+        let clean_trace = clean_trace.borrow();
+        let faulty_trace = faulty_trace.borrow();
+        
+        let mut ticks : Vec<u64> = clean_trace.iter().chain(faulty_trace.iter()).map(|change| { change.0 }).collect();
+        ticks.sort();
+        
+        let first_difference = ticks.into_iter().find(|tick| {
+            state_at(&clean_trace[], *tick) != state_at(&faulty_trace[], *tick)
+        });
+        
+        if let Some(first_difference) = first_difference {
+            corruptions.push(Corruption {
+                output: index,
+                name: faulty.node_name(outputs[index]),
+                first_difference: first_difference,
+                wrong_at_end: state_at(&clean_trace[], until) != state_at(&faulty_trace[], until),
+            });
+        }
+    }
+    
+    corruptions
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCreator, NodeIndex, NodeCollection, LineState};
+    use logic_gates::{AndGate, NWayAnd};
+    use storage::Register;
+    use pin::Pin;
+    use stimulus::Stimulus;
+    use super::{FaultReport, stuck_at_coverage, play_with_faults, upset_experiment, TransientFault, Upset};
+    
+    fn and_gate(creator: &mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>) {
+        let and = AndGate::new(creator);
+        ([and.a, and.b].to_vec(), [and.output].to_vec())
+    }
+    
+    fn logtime_and(creator: &mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>) {
+        let and = NWayAnd::new_logtime(creator, 2);
+        (and.inputs.clone(), [and.output].to_vec())
+    }
+    
+    #[test]
+    fn test_nodes_without_elements_are_detected() {
+        // The inputs of a logtime NWayAnd are only linked to, nothing ever writes them
+        let report = stuck_at_coverage(logtime_and, &[
+            (&[0,0], &[0]),
+            (&[0,1], &[0]),
+            (&[1,0], &[0]),
+            (&[1,1], &[1]),
+        ]);
+        assert!(!report.undetected.contains(&("NWayAnd0.input0".to_string(), LineState::Low)));
+        assert!(!report.undetected.contains(&("NWayAnd0.input0".to_string(), LineState::High)));
+        
+        let empty = FaultReport { fault_count: 0, undetected: Vec::new() };
+        assert_eq!(empty.coverage(), 1.0);
+    }
+    
+    #[test]
+    fn test_more_cases_detect_more_faults() {
+        let partial = stuck_at_coverage(and_gate, &[
+            (&[1,1], &[1]),
+        ]);
+        let full = stuck_at_coverage(and_gate, &[
+            (&[0,0], &[0]),
+            (&[0,1], &[0]),
+            (&[1,0], &[0]),
+            (&[1,1], &[1]),
+        ]);
+        
+        let a_stuck_high = ("AndGate0/Nand0.a".to_string(), LineState::High);
+        assert!(partial.undetected.contains(&a_stuck_high));
+        assert!(!full.undetected.contains(&a_stuck_high));
+        assert!(full.coverage() > partial.coverage());
+    }
+    
+    #[test]
+    fn test_overlapping_faults() {
+        let forced_after = |until: u64| -> Option<LineState> {
+            let mut c = NodeCollection::new();
+            let mut creator = NodeCreator::new(&c);
+            let pin = Pin::new(&mut creator);
+            c.absorb(creator);
+            pin.node.write(LineState::Low, &mut c);
+            
+            play_with_faults(&mut c, &[
+                TransientFault { node: pin.node, upset: Upset::Force(LineState::High), start: 100, end: 1000 },
+                TransientFault { node: pin.node, upset: Upset::Force(LineState::Low), start: 500, end: 700 },
+            ], until);
+            c.forced_state(pin.node)
+        };
+        assert_eq!(forced_after(600), Some(LineState::Low));
+        // The second fault ending leaves the first in place
+        assert_eq!(forced_after(800), Some(LineState::High));
+        assert_eq!(forced_after(1200), None);
+    }
+    
+    #[test]
+    fn test_register_upset() {
+        let corruptions = upset_experiment(|c: &mut NodeCollection| {
+            let mut creator = NodeCreator::new(c);
+            let register = Register::new(&mut creator, 4);
+            let clock = Pin::new(&mut creator);
+            let data : Vec<Pin> = range(0, 4).map(|_| { Pin::new(&mut creator) }).collect();
+            creator.wire(clock.node, register.clock);
+            for (pin, input) in data.iter().zip(register.inputs.iter()) {
+                creator.wire(pin.node, *input);
+            }
+            c.absorb(creator);
+            
+            let data_nodes : Vec<NodeIndex> = data.iter().map(|pin| { pin.node }).collect();
+            Stimulus::new()
+                .at(0, clock.node, LineState::Low)
+                .bus_at(0, &data_nodes[], 0b1010)
+                .at(1000, clock.node, LineState::High)
+                .at(2000, clock.node, LineState::Low)
+                .apply(c);
+            
+            (register.outputs.clone(), [TransientFault{
+                node: register.outputs[1],
+                upset: Upset::Flip,
+                start: 5000,
+                end: 5500,
+            }].to_vec())
+        }, 8000);
+        
+        assert_eq!(corruptions.len(), 1);
+        assert_eq!(corruptions[0].output, 1);
+        assert!(corruptions[0].first_difference >= 5000);
+        assert!(corruptions[0].wrong_at_end);
+    }
+}
@@ -0,0 +1,770 @@
+use std::collections::HashMap;
+
+use sim::{NodeCreator, NodeIndex};
+use logic_gates::{NandGate, AndGate, OrGate, XorGate, NotGate};
+use adder::{HalfAdder, Adder, RippleCarryAdder};
+use mux::{BitMux, Mux, MuxN};
+use demux::{BitDemux, Demux, DemuxN};
+use storage::{NotSRLatch, DFlipFlop, Register};
+use rom::ConstantBits;
+use component::Component;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Symbol(char),
+}
+
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars : Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().map(|c| { *c }).collect()), line));
+        } else if c.is_numeric() {
+            let start = i;
+            while i < chars.len() && chars[i].is_alphanumeric() {
+                i += 1;
+            }
+            let text : String = chars[start..i].iter().map(|c| { *c }).collect();
+            tokens.push((Token::Number(try!(parse_number(&text[], line))), line));
+        } else if "(){}[]:;,.=".contains_char(c) {
+            tokens.push((Token::Symbol(c), line));
+            i += 1;
+        } else {
+            return Err(format!("line {}: unexpected character {}", line, c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads "12", "0x1f" or "0b101".
+fn parse_number(text: &str, line: usize) -> Result<u64, String> {
+    let (radix, digits) = if text.starts_with("0x") {
+        (16, &text[2..])
+    } else if text.starts_with("0b") {
+        (2, &text[2..])
+    } else {
+        (10, text)
+    };
+    if digits.is_empty() {
+        return Err(format!("line {}: bad number {}", line, text));
+    }
+    let mut value : u64 = 0;
+    for digit in digits.chars() {
+        match digit.to_digit(radix) {
+            Some(digit) => {
+                value = match value.checked_mul(radix as u64).and_then(|value| { value.checked_add(digit as u64) }) {
+                    Some(value) => value,
+                    None => { return Err(format!("line {}: {} does not fit in 64 bits", line, text)); }
+                };
+            },
+            None => { return Err(format!("line {}: bad number {}", line, text)); }
+        }
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Signal(Option<String>, String, Option<(usize, usize)>), // The instance, for its ports, then the name and the bits from start up to end
+    Number(u64),
+    List(Vec<Expr>), // Least significant part first
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SignalKind {
+    Input,
+    Output,
+    Wire,
+}
+
+#[derive(Debug, Clone)]
+struct Signal {
+    name: String,
+    kind: SignalKind,
+    width: usize,
+    line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Instance {
+    name: String,
+    component: String,
+    params: Vec<usize>,
+    connections: Vec<(String, Expr)>,
+    line: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Instance(Instance),
+    Connect(Expr, Expr, usize),
+}
+
+#[derive(Debug, Clone)]
+struct Module {
+    name: String,
+    signals: Vec<Signal>, // The ports in order, then the wires
+    statements: Vec<Statement>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        if self.position < self.tokens.len() {
+            self.tokens[self.position].1
+        } else {
+            self.tokens.last().map(|&(_, line)| { line }).unwrap_or(1)
+        }
+    }
+    
+    fn error<T>(&self, message: String) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line(), message))
+    }
+    
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|&(ref token, _)| { token })
+    }
+    
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+    
+    fn is_ident(&self, ident: &str) -> bool {
+        match self.peek() {
+            Some(&Token::Ident(ref name)) => &name[] == ident,
+            _ => false,
+        }
+    }
+    
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), String> {
+        if self.is_symbol(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error(format!("expected {}", symbol))
+        }
+    }
+    
+    fn expect_ident(&mut self) -> Result<String, String> {
+        match self.peek().map(|token| { token.clone() }) {
+            Some(Token::Ident(name)) => {
+                self.position += 1;
+                Ok(name)
+            },
+            _ => self.error("expected a name".to_string()),
+        }
+    }
+    
+    fn expect_number(&mut self) -> Result<u64, String> {
+        match self.peek().map(|token| { token.clone() }) {
+            Some(Token::Number(value)) => {
+                self.position += 1;
+                Ok(value)
+            },
+            _ => self.error("expected a number".to_string()),
+        }
+    }
+    
+    // A name and its width, as "x" or "x[4]".
+    fn parse_declaration(&mut self, kind: SignalKind) -> Result<Signal, String> {
+        let line = self.line();
+        let name = try!(self.expect_ident());
+        let width = if self.is_symbol('[') {
+            try!(self.expect_symbol('['));
+            let width = try!(self.expect_number()) as usize;
+            try!(self.expect_symbol(']'));
+            if width == 0 {
+                return Err(format!("line {}: {} has no bits", line, name));
+            }
+            width
+        } else {
+            1
+        };
+        Ok(Signal {
+            name: name,
+            kind: kind,
+            width: width,
+            line: line,
+        })
+    }
+    
+    fn parse_module(&mut self) -> Result<Module, String> {
+        if !self.is_ident("module") {
+            return self.error("expected module".to_string());
+        }
+        self.position += 1;
+        let mut module = Module {
+            name: try!(self.expect_ident()),
+            signals: Vec::new(),
+            statements: Vec::new(),
+        };
+        
+        try!(self.expect_symbol('('));
+        while !self.is_symbol(')') {
+            let kind = if self.is_ident("in") {
+                SignalKind::Input
+            } else if self.is_ident("out") {
+                SignalKind::Output
+            } else {
+                return self.error("expected in or out".to_string());
+            };
+            self.position += 1;
+            module.signals.push(try!(self.parse_declaration(kind)));
+            if !self.is_symbol(')') {
+                try!(self.expect_symbol(','));
+            }
+        }
+        try!(self.expect_symbol(')'));
+        
+        try!(self.expect_symbol('{'));
+        while !self.is_symbol('}') {
+            if self.peek().is_none() {
+                return self.error(format!("module {} is not closed", module.name));
+            }
+            
+            let line = self.line();
+            if self.is_ident("wire") {
+                self.position += 1;
+                loop {
+                    module.signals.push(try!(self.parse_declaration(SignalKind::Wire)));
+                    if !self.is_symbol(',') {
+                        break;
+                    }
+                    self.position += 1;
+                }
+            } else if self.is_ident("let") {
+                self.position += 1;
+                let name = try!(self.expect_ident());
+                try!(self.expect_symbol('='));
+                let component = try!(self.expect_ident());
+                
+                let mut params = Vec::new();
+                if self.is_symbol('(') {
+                    self.position += 1;
+                    while !self.is_symbol(')') {
+                        params.push(try!(self.expect_number()) as usize);
+                        if !self.is_symbol(')') {
+                            try!(self.expect_symbol(','));
+                        }
+                    }
+                    try!(self.expect_symbol(')'));
+                }
+                
+                let mut connections = Vec::new();
+                if self.is_symbol('{') {
+                    self.position += 1;
+                    while !self.is_symbol('}') {
+                        let port = try!(self.expect_ident());
+                        try!(self.expect_symbol(':'));
+                        connections.push((port, try!(self.parse_expr())));
+                        if !self.is_symbol('}') {
+                            try!(self.expect_symbol(','));
+                        }
+                    }
+                    try!(self.expect_symbol('}'));
+                }
+                
+                module.statements.push(Statement::Instance(Instance {
+                    name: name,
+                    component: component,
+                    params: params,
+                    connections: connections,
+                    line: line,
+                }));
+            } else {
+                let target = try!(self.parse_expr());
+                try!(self.expect_symbol('='));
+                let value = try!(self.parse_expr());
+                module.statements.push(Statement::Connect(target, value, line));
+            }
+            try!(self.expect_symbol(';'));
+        }
+        try!(self.expect_symbol('}'));
+        Ok(module)
+    }
+    
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        if self.is_symbol('[') {
+            self.position += 1;
+            let mut parts = Vec::new();
+            while !self.is_symbol(']') {
+                parts.push(try!(self.parse_expr()));
+                if !self.is_symbol(']') {
+                    try!(self.expect_symbol(','));
+                }
+            }
+            try!(self.expect_symbol(']'));
+            return Ok(Expr::List(parts));
+        }
+        let number = match self.peek() {
+            Some(&Token::Number(value)) => Some(value),
+            _ => None,
+        };
+        if let Some(value) = number {
+            self.position += 1;
+            return Ok(Expr::Number(value));
+        }
+        
+        let mut instance = None;
+        let mut name = try!(self.expect_ident());
+        if self.is_symbol('.') {
+            self.position += 1;
+            instance = Some(name);
+            name = try!(self.expect_ident());
+        }
+        
+        let select = if self.is_symbol('[') {
+            self.position += 1;
+            let start = try!(self.expect_number()) as usize;
+            let end = if self.is_symbol('.') {
+                try!(self.expect_symbol('.'));
+                try!(self.expect_symbol('.'));
+                try!(self.expect_number()) as usize
+            } else {
+                start + 1
+            };
+            try!(self.expect_symbol(']'));
+            Some((start, end))
+        } else {
+            None
+        };
+        Ok(Expr::Signal(instance, name, select))
+    }
+}
+
+fn parse_hdl(source: &str) -> Result<Vec<Module>, String> {
+    let mut parser = Parser {
+        tokens: try!(lex(source)),
+        position: 0,
+    };
+    let mut modules = Vec::new();
+    while parser.peek().is_some() {
+        modules.push(try!(parser.parse_module()));
+    }
+    Ok(modules)
+}
+
+// Ports by name, least significant bit first.
+type Ports = HashMap<String, Vec<NodeIndex>>;
+
+fn flatten(mut words: Vec<Vec<NodeIndex>>) -> Vec<NodeIndex> {
+    let mut nodes = Vec::new();
+    for word in words.iter_mut() {
+        nodes.append(word);
+    }
+    nodes
+}
+
+// The ports of a component with only single-bit ports, each as a one-bit bus.
+fn single_bit_ports(component: &Component) -> Vec<(&'static str, Vec<NodeIndex>)> {
+    component.ports().into_iter().map(|(name, node)| { (name, vec![node]) }).collect()
+}
+
+// Builds one of the library's components, if there is one called `component`. Ports with a
+// word per input or output, like MuxN's inputs, are flattened into one bus, first word lowest.
+fn build_library(component: &str, params: &[usize], line: usize, creator: &mut NodeCreator) -> Result<Option<Ports>, String> {
+    let param_count = match component {
+        "NandGate" | "AndGate" | "OrGate" | "XorGate" | "NotGate" | "HalfAdder" | "Adder" |
+        "BitMux" | "BitDemux" | "NotSRLatch" | "DFlipFlop" => 0,
+        "RippleCarryAdder" | "Mux" | "Demux" | "Register" => 1,
+        "MuxN" | "DemuxN" => 2,
+        _ => { return Ok(None); }
+    };
+    if params.len() != param_count {
+        return Err(format!("line {}: {} takes {} parameters, not {}", line, component, param_count, params.len()));
+    }
+    if params.contains(&0) {
+        return Err(format!("line {}: {} cannot have a size of 0", line, component));
+    }
+    
+    let ports : Vec<(&str, Vec<NodeIndex>)> = match component {
+        "NandGate" => single_bit_ports(&NandGate::new(creator)),
+        "AndGate" => single_bit_ports(&AndGate::new(creator)),
+        "OrGate" => single_bit_ports(&OrGate::new(creator)),
+        "XorGate" => single_bit_ports(&XorGate::new(creator)),
+        "NotGate" => single_bit_ports(&NotGate::new(creator)),
+        "HalfAdder" => single_bit_ports(&HalfAdder::new(creator)),
+        "Adder" => single_bit_ports(&Adder::new(creator)),
+        "RippleCarryAdder" => {
+            let adder = RippleCarryAdder::new(creator, params[0]);
+            vec![("a", adder.a), ("b", adder.b), ("carry_in", vec![adder.carry_in]),
+                 ("sum", adder.sum), ("carry_out", vec![adder.carry_out])]
+        },
+        "BitMux" => single_bit_ports(&BitMux::new(creator)),
+        "Mux" => {
+            let mux = Mux::new(params[0], creator);
+            vec![("a", mux.a), ("b", mux.b), ("select", vec![mux.select]), ("output", mux.output)]
+        },
+        "MuxN" => {
+            let mux = MuxN::new(params[0], params[1], creator);
+            vec![("inputs", flatten(mux.inputs)), ("select", mux.select), ("output", mux.output)]
+        },
+        "BitDemux" => single_bit_ports(&BitDemux::new(creator)),
+        "Demux" => {
+            let demux = Demux::new(params[0], creator);
+            vec![("input", demux.input), ("select", vec![demux.select]), ("enable", vec![demux.enable]),
+                 ("output_a", demux.output_a), ("output_b", demux.output_b)]
+        },
+        "DemuxN" => {
+            let demux = DemuxN::new(params[0], params[1], creator);
+            vec![("input", demux.input), ("select", demux.select), ("enable", vec![demux.enable]),
+                 ("outputs", flatten(demux.outputs))]
+        },
+        "NotSRLatch" => single_bit_ports(&NotSRLatch::new(creator)),
+        "DFlipFlop" => single_bit_ports(&DFlipFlop::new(creator)),
+        _ => {
+            let register = Register::new(creator, params[0]);
+            vec![("inputs", register.inputs), ("outputs", register.outputs), ("clock", vec![register.clock])]
+        },
+    };
+    Ok(Some(ports.into_iter().map(|(name, nodes)| { (name.to_string(), nodes) }).collect()))
+}
+
+/// The ports of an elaborated module, least significant bit first, and the names of its
+/// inputs and outputs in the order they were declared.
+pub struct HdlModule {
+    pub ports: HashMap<String, Vec<NodeIndex>>,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+impl HdlModule {
+    pub fn port(&self, name: &str) -> Option<Vec<NodeIndex>> {
+        self.ports.get(name).map(|nodes| { nodes.clone() })
+    }
+}
+
+// What the names in a module refer to: its signals, and the ports of its instances.
+struct Scope {
+    signals: Ports,
+    instances: HashMap<String, Ports>,
+}
+
+impl Scope {
+    fn resolve(&self, expr: &Expr, width: Option<usize>, line: usize, creator: &mut NodeCreator) -> Result<Vec<NodeIndex>, String> {
+        match *expr {
+            Expr::Signal(ref instance, ref name, select) => {
+                let nodes = match *instance {
+                    Some(ref instance) => {
+                        match self.instances.get(instance) {
+                            Some(ports) => match ports.get(name) {
+                                Some(nodes) => nodes,
+                                None => { return Err(format!("line {}: {} has no port {}", line, instance, name)); }
+                            },
+                            None => { return Err(format!("line {}: no instance named {}", line, instance)); }
+                        }
+                    },
+                    None => {
+                        match self.signals.get(name) {
+                            Some(nodes) => nodes,
+                            None if self.instances.contains_key(name) => {
+                                return Err(format!("line {}: {} is an instance, not a signal", line, name));
+                            },
+                            None => { return Err(format!("line {}: undeclared signal {}", line, name)); }
+                        }
+                    }
+                };
+                match select {
+                    None => Ok(nodes.clone()),
+                    Some((start, end)) => {
+                        if start >= end || end > nodes.len() {
+                            return Err(format!("line {}: bits {}..{} of {} are out of range", line, start, end, name));
+                        }
+                        Ok(nodes[start..end].to_vec())
+                    }
+                }
+            },
+            Expr::Number(value) => {
+                let width = width.unwrap_or(1);
+                if width > 64 || (width < 64 && value >> width != 0) {
+                    return Err(format!("line {}: {} does not fit in {} bits", line, value, width));
+                }
+                Ok(ConstantBits::new(&ConstantBits::make_bits(value, width)[], creator).bits)
+            },
+            Expr::List(ref parts) => {
+                let mut nodes = Vec::new();
+                for part in parts.iter() {
+                    nodes.push_all(&try!(self.resolve(part, None, line, creator))[]);
+                }
+                Ok(nodes)
+            },
+        }
+    }
+    
+    fn connect(&self, nodes: &[NodeIndex], value: &Expr, line: usize, creator: &mut NodeCreator) -> Result<(), String> {
+        let value = try!(self.resolve(value, Some(nodes.len()), line, creator));
+        if value.len() != nodes.len() {
+            return Err(format!("line {}: connecting {} bits to {}", line, value.len(), nodes.len()));
+        }
+        creator.multiwire(&value[], nodes);
+        Ok(())
+    }
+}
+
+struct Elaborator<'m> {
+    modules: &'m [Module],
+    stack: Vec<String>, // The modules being elaborated, to catch a module containing itself
+}
+
+impl<'m> Elaborator<'m> {
+    fn elaborate(&mut self, module: &'m Module, creator: &mut NodeCreator) -> Result<Ports, String> {
+        if self.stack.contains(&module.name) {
+            return Err(format!("module {} contains itself", module.name));
+        }
+        self.stack.push(module.name.clone());
+        creator.begin_component(&module.name[]);
+        let ports = self.elaborate_body(module, creator);
+        creator.end_component();
+        self.stack.pop();
+        ports
+    }
+    
+    fn elaborate_body(&mut self, module: &'m Module, creator: &mut NodeCreator) -> Result<Ports, String> {
+        let mut scope = Scope {
+            signals: HashMap::new(),
+            instances: HashMap::new(),
+        };
+        for signal in module.signals.iter() {
+            if scope.signals.contains_key(&signal.name) {
+                return Err(format!("line {}: {} is declared twice", signal.line, signal.name));
+            }
+            let nodes = range(0, signal.width).map(|bit| {
+                if signal.width == 1 {
+                    creator.new_named_node(&signal.name[])
+                } else {
+                    creator.new_named_node(&format!("{}[{}]", signal.name, bit)[])
+                }
+            }).collect();
+            scope.signals.insert(signal.name.clone(), nodes);
+        }
+        
+        // Every instance is built before anything is connected, so connections can use the
+        // ports of instances further down.
+        for statement in module.statements.iter() {
+            if let Statement::Instance(ref instance) = *statement {
+                if scope.signals.contains_key(&instance.name) || scope.instances.contains_key(&instance.name) {
+                    return Err(format!("line {}: {} is declared twice", instance.line, instance.name));
+                }
+                let ports = try!(self.instantiate(instance, creator));
+                scope.instances.insert(instance.name.clone(), ports);
+            }
+        }
+        
+        for statement in module.statements.iter() {
+            match *statement {
+                Statement::Instance(ref instance) => {
+                    let ports = scope.instances.get(&instance.name).unwrap().clone();
+                    for &(ref port, ref value) in instance.connections.iter() {
+                        let nodes = match ports.get(port) {
+                            Some(nodes) => nodes,
+                            None => { return Err(format!("line {}: {} has no port {}", instance.line, instance.component, port)); }
+                        };
+                        try!(scope.connect(&nodes[], value, instance.line, creator));
+                    }
+                },
+                Statement::Connect(ref target, ref value, line) => {
+                    if let Expr::Number(_) = *target {
+                        return Err(format!("line {}: cannot connect to a number", line));
+                    }
+                    let nodes = try!(scope.resolve(target, None, line, creator));
+                    try!(scope.connect(&nodes[], value, line, creator));
+                },
+            }
+        }
+        
+        let mut ports = HashMap::new();
+        for signal in module.signals.iter().filter(|signal| { signal.kind != SignalKind::Wire }) {
+            ports.insert(signal.name.clone(), scope.signals.get(&signal.name).unwrap().clone());
+        }
+        Ok(ports)
+    }
+    
+    fn instantiate(&mut self, instance: &Instance, creator: &mut NodeCreator) -> Result<Ports, String> {
+        if let Some(ports) = try!(build_library(&instance.component[], &instance.params[], instance.line, creator)) {
+            return Ok(ports);
+        }
+        
+        let modules = self.modules;
+        match modules.iter().find(|module| { module.name == instance.component }) {
+            Some(module) => {
+                if !instance.params.is_empty() {
+                    return Err(format!("line {}: module {} takes no parameters", instance.line, module.name));
+                }
+                self.elaborate(module, creator).map_err(|error| {
+                    if error.starts_with("line ") { error } else { format!("line {}: {}", instance.line, error) }
+                })
+            },
+            None => Err(format!("line {}: no component or module named {}", instance.line, instance.component)),
+        }
+    }
+}
+
+/// Builds the module `top` of an HDL source, along with everything it instantiates, so that
+/// circuits can be put together out of the library's components without linking nodes by hand:
+///
+/// ```text
+/// // Adds two 2-bit numbers packed into x
+/// module add2(in x[4], out sum[3]) {
+///     wire carry;
+///     let adder = RippleCarryAdder(2) { a: x[0..2], b: x[2..4], carry_in: 0, carry_out: carry };
+///     sum = [adder.sum, carry];
+/// }
+/// ```
+///
+/// Buses are least significant bit first: `x[1]` is one bit, `x[0..2]` the bits from 0 up to
+/// but not including 2, and `[a, b]` puts `a` below `b`. `let` instantiates a library component
+/// or another module of the source, with any sizes it takes in brackets, and can connect its
+/// ports in braces; `=` connects anything else, including ports of instances. A number takes
+/// the width of what it is connected to, or one bit inside `[...]`. Errors give their line.
+pub fn read_hdl(source: &str, top: &str, creator: &mut NodeCreator) -> Result<HdlModule, String> {
+    let modules = try!(parse_hdl(source));
+    let module = match modules.iter().find(|module| { &module.name[] == top }) {
+        Some(module) => module,
+        None => { return Err(format!("no module named {}", top)); }
+    };
+    
+    let mut elaborator = Elaborator {
+        modules: &modules[],
+        stack: Vec::new(),
+    };
+    let ports = try!(elaborator.elaborate(module, creator));
+    let names_of = |kind: SignalKind| -> Vec<String> {
+        module.signals.iter().filter(|signal| { signal.kind == kind }).map(|signal| { signal.name.clone() }).collect()
+    };
+    Ok(HdlModule {
+        ports: ports,
+        inputs: names_of(SignalKind::Input),
+        outputs: names_of(SignalKind::Output),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator};
+    use truth_table::check_truth_table;
+    use super::read_hdl;
+    
+    #[test]
+    fn test_instances() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_hdl("
+                module full_adder(in a, in b, in carry_in, out sum, out carry_out) {
+                    let first = HalfAdder { a: a, b: b };
+                    let second = HalfAdder { a: first.sum, b: carry_in, sum: sum };
+                    let either = OrGate { a: first.carry, b: second.carry, output: carry_out };
+                }
+            ", "full_adder", creator).unwrap();
+            
+            assert_eq!(module.inputs, vec!["a", "b", "carry_in"]);
+            let port = |name: &str| { module.port(name).unwrap()[0] };
+            (vec![port("a"), port("b"), port("carry_in")], vec![port("sum"), port("carry_out")])
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,0,0], &[1,0]),
+            (&[0,1,0], &[1,0]),
+            (&[1,1,0], &[0,1]),
+            (&[0,0,1], &[1,0]),
+            (&[1,0,1], &[0,1]),
+            (&[0,1,1], &[0,1]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_buses() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_hdl("
+                // Adds two 2-bit numbers packed into x
+                module add2(in x[4], out sum[3]) {
+                    wire carry;
+                    let adder = RippleCarryAdder(2) { a: x[0..2], b: x[2..4], carry_in: 0, carry_out: carry };
+                    sum = [adder.sum, carry];
+                }
+            ", "add2", creator).unwrap();
+            (module.port("x").unwrap(), module.port("sum").unwrap())
+        }, &[
+            (&[0,0, 0,0], &[0,0,0]),
+            (&[1,0, 1,0], &[0,1,0]),
+            (&[0,1, 1,0], &[1,1,0]),
+            (&[1,1, 1,1], &[0,1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_modules() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_hdl("
+                module top(in select[2], out chosen[2], out high) {
+                    // The four words are 3, 2, 1 and 0
+                    let inner = pick { words: 0x1b, select: select, chosen: chosen };
+                    high = inner.chosen[1];
+                }
+                
+                module pick(in words[8], in select[2], out chosen[2]) {
+                    let mux = MuxN(2, 4) { inputs: words, select: select, output: chosen };
+                }
+            ", "top", creator).unwrap();
+            
+            let mut outputs = module.port("chosen").unwrap();
+            outputs.push_all(&module.port("high").unwrap()[]);
+            (module.port("select").unwrap(), outputs)
+        }, &[
+            (&[0,0], &[1,1,1]),
+            (&[1,0], &[0,1,1]),
+            (&[0,1], &[1,0,0]),
+            (&[1,1], &[0,0,0]),
+        ]);
+    }
+    
+    #[test]
+    fn test_errors() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let error = read_hdl("module m(in a, out y) {\n    let gate = NotGate { input: b, output: y };\n}\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 2: undeclared signal b".to_string()));
+        
+        let error = read_hdl("module m(in a[4], out y[2]) {\n    let gate = Mux(2) { a: a[0..2] };\n    y = a;\n}\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 3: connecting 4 bits to 2".to_string()));
+        
+        let error = read_hdl("module m(in a) {\n\n    let adder = Adder(3);\n}\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 3: Adder takes 0 parameters, not 1".to_string()));
+        
+        let error = read_hdl("module m(in a) {\n    let thing = Widget;\n}\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 2: no component or module named Widget".to_string()));
+        
+        let error = read_hdl("module m(in a[2]) {\n    a[1..3] = 0;\n}\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 2: bits 1..3 of a are out of range".to_string()));
+        
+        let error = read_hdl("module m(in a) {\n    a = 0x10000000000000000;\n}\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 2: 0x10000000000000000 does not fit in 64 bits".to_string()));
+        
+        // The failed reads left no component open, so the five m's before this one are its siblings
+        let module = read_hdl("module m(in a, out y) {\n    y = a;\n}\n", "m", &mut creator).unwrap();
+        let a = module.port("a").unwrap()[0];
+        assert!(creator.node_names().iter().any(|&(node, ref name)| { node == a && &name[] == "m5.a" }));
+    }
+}
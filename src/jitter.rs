@@ -0,0 +1,150 @@
+use sim::NodeCollection;
+
+/// Randomly stretches or shrinks delays as they are absorbed, to expose races that only
+/// happen under process variation. The same seed always gives the same delays.
+#[derive(Debug, Copy, Clone)]
+pub struct Jitter {
+    pub seed: u64,
+    pub spread_percent: u32, // Each delay is scaled by between 100-spread and 100+spread percent
+}
+
+impl Jitter {
+    pub fn scaler(&self) -> DelayScaler {
+        assert!(self.spread_percent <= 100);
+        DelayScaler {
+            // xorshift gets stuck on an all-zero state
+            state: if self.seed == 0 { 0x9E3779B97F4A7C15 } else { self.seed },
+            spread_percent: self.spread_percent,
+        }
+    }
+}
+
+pub struct DelayScaler {
+    state: u64,
+    spread_percent: u32,
+}
+
+impl DelayScaler {
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+    
+    pub fn next_percent(&mut self) -> u32 {
+        let choices = (2*self.spread_percent + 1) as u64;
+        100 - self.spread_percent + (self.next_u64() % choices) as u32
+    }
+}
+
+pub struct MonteCarloReport {
+    pub runs: usize,
+    pub failed_seeds: Vec<u64>,
+}
+
+impl MonteCarloReport {
+    /// The fraction of runs that failed, or 0 if there were no runs.
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        (self.failed_seeds.len() as f64) / (self.runs as f64)
+    }
+}
+
+/// Runs `trial` once for each of `runs` consecutive seeds, each time on a fresh collection
+/// with jitter set. The trial builds and absorbs its circuit, and returns whether it behaved.
+pub fn monte_carlo<F>(runs: usize, first_seed: u64, spread_percent: u32, mut trial: F) -> MonteCarloReport
+    where F: FnMut(&mut NodeCollection) -> bool
+{
+    let mut failed_seeds = Vec::new();
+    for run in range(0, runs) {
+        let seed = first_seed + run as u64;
+        let mut c = NodeCollection::new();
+        c.set_jitter(Jitter{
+            seed: seed,
+            spread_percent: spread_percent,
+        });
+        
+        if !trial(&mut c) {
+            failed_seeds.push(seed);
+        }
+    }
+    
+    MonteCarloReport {
+        runs: runs,
+        failed_seeds: failed_seeds,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay};
+    use storage::DFlipFlop;
+    use logic_gates::NotGate;
+    use pin::Pin;
+    use super::{Jitter, monte_carlo};
+    
+    fn flip_flop_stores(c: &mut NodeCollection) -> bool {
+        let mut creator = NodeCreator::new(c);
+        let ff = DFlipFlop::new(&mut creator);
+        let clock = Pin::new(&mut creator);
+        let data = Pin::new(&mut creator);
+        creator.wire(clock.node, ff.clock);
+        creator.wire(data.node, ff.data);
+        c.absorb(creator);
+        
+        clock.node.write(LineState::Low, c);
+        data.node.write(LineState::High, c);
+        clock.node.write_later(LineState::High, PropogationDelay(2000), c);
+        clock.node.write_later(LineState::Low, PropogationDelay(4000), c);
+        while c.play() {}
+        
+        ff.q.read(c) == LineState::High
+    }
+    
+    // When a change on the input has made it through a chain of inverters.
+    fn chain_settles_at(seed: u64) -> u64 {
+        let mut c = NodeCollection::new();
+        c.set_jitter(Jitter {
+            seed: seed,
+            spread_percent: 50,
+        });
+        let mut creator = NodeCreator::new(&c);
+        let input = Pin::new(&mut creator);
+        let mut last = input.node;
+        for _ in range(0, 8) {
+            let not = NotGate::new(&mut creator);
+            creator.wire(last, not.input);
+            last = not.output;
+        }
+        c.absorb(creator);
+        
+        input.node.write(LineState::High, &mut c);
+        while c.play() {}
+        c.current_tick
+    }
+    
+    #[test]
+    fn test_seeds_change_timing() {
+        let ticks : Vec<u64> = range(1, 6).map(chain_settles_at).collect();
+        assert!(ticks.iter().any(|tick| { *tick != ticks[0] }), "every seed settled at {}", ticks[0]);
+        assert_eq!(chain_settles_at(3), ticks[2]);
+    }
+    
+    #[test]
+    fn test_no_spread_never_fails() {
+        let report = monte_carlo(5, 1, 0, flip_flop_stores);
+        assert_eq!(report.failure_rate(), 0.0);
+        assert_eq!(monte_carlo(0, 1, 0, flip_flop_stores).failure_rate(), 0.0);
+    }
+    
+    #[test]
+    fn test_seeds_are_repeatable() {
+        let first = monte_carlo(20, 100, 60, flip_flop_stores);
+        let second = monte_carlo(20, 100, 60, flip_flop_stores);
+        assert_eq!(first.failed_seeds, second.failed_seeds);
+    }
+}
@@ -0,0 +1,184 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::io;
+use std::io::Write;
+use std::fs::File;
+use std::path::Path;
+
+use sim::{NodeCollection, NodeIndex, LineState, PendingEvent};
+use snapshot::format_state;
+
+fn quote(text: &str) -> String {
+    let mut quoted = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '"' => { quoted.push_str("\\\""); },
+            '\\' => { quoted.push_str("\\\\"); },
+            c if (c as u32) < 0x20 => { quoted.push_str(&format!("\\u{:04x}", c as u32)[]); },
+            c => { quoted.push(c); },
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct Recorder {
+    output: Box<Write>,
+    error: Option<io::Error>, // The first write that failed; later lines are dropped
+    names: Vec<String>,
+    traced: Vec<bool>,
+    states: Vec<LineState>, // Of the traced nodes, as of the last event on each
+    snapshot_interval: u64,
+    next_snapshot: u64,
+}
+
+impl Recorder {
+    fn write_line(&mut self, line: String) {
+        if self.error.is_none() {
+            if let Err(error) = self.output.write_all(format!("{}\n", line).as_bytes()) {
+                self.error = Some(error);
+            }
+        }
+    }
+    
+    fn snapshot(&mut self, tick: u64) {
+        let states : Vec<String> = range(0, self.names.len()).filter(|index| { self.traced[*index] }).map(|index| {
+            format!("{}:{}", quote(&self.names[index][]), quote(format_state(self.states[index])))
+        }).collect();
+        self.write_line(format!("{{\"snapshot\":{},\"states\":{{{}}}}}", tick, states.connect(",")));
+    }
+    
+    fn event(&mut self, event: &PendingEvent, state: LineState) {
+        while self.snapshot_interval > 0 && event.time >= self.next_snapshot {
+            let boundary = self.next_snapshot;
+            self.snapshot(boundary);
+            self.next_snapshot = boundary + self.snapshot_interval;
+        }
+        
+        let index = event.node.0;
+        if index >= self.traced.len() || !self.traced[index] {
+            return;
+        }
+        self.states[index] = state;
+        let forcer = if event.forcer.0 < self.names.len() { quote(&self.names[event.forcer.0][]) } else { quote(&format!("#{}", event.forcer.0)[]) };
+        self.write_line(format!("{{\"tick\":{},\"node\":{},\"state\":{},\"forcer\":{}}}",
+            event.time, quote(&self.names[index][]), quote(format_state(state)), forcer));
+    }
+}
+
+/// A log of a run as JSON lines: one object per played event with its tick, node name,
+/// the node's resolved state afterwards and the name of the node that forced it, plus
+/// snapshots of every traced node's state. Each line is written out as it is produced.
+pub struct JsonTrace {
+    recorder: Rc<RefCell<Recorder>>,
+}
+
+impl JsonTrace {
+    /// Starts logging to `output` events on nodes whose names start with one of `prefixes`,
+    /// or on every node if there are none. A snapshot is written now, and then for every
+    /// multiple of `snapshot_interval` ticks that play crosses, giving the states as that
+    /// tick began; 0 turns them off. Nodes absorbed after this are not traced.
+    pub fn attach(c: &mut NodeCollection, output: Box<Write>, prefixes: &[&str], snapshot_interval: u64) -> JsonTrace {
+        let names : Vec<String> = range(0, c.node_count()).map(|index| { c.node_name(NodeIndex(index)) }).collect();
+        let traced = names.iter().map(|name| {
+            prefixes.is_empty() || prefixes.iter().any(|prefix| { name.starts_with(*prefix) })
+        }).collect();
+        let states = range(0, names.len()).map(|index| { NodeIndex(index).read(c) }).collect();
+        let tick = c.current_tick;
+        
+        let mut recorder = Recorder {
+            output: output,
+            error: None,
+            names: names,
+            traced: traced,
+            states: states,
+            snapshot_interval: snapshot_interval,
+            next_snapshot: if snapshot_interval > 0 { (tick / snapshot_interval + 1) * snapshot_interval } else { 0 },
+        };
+        recorder.snapshot(tick);
+        
+        let recorder = Rc::new(RefCell::new(recorder));
+        let listener = recorder.clone();
+        c.on_event(move |event, state| {
+            listener.borrow_mut().event(event, state);
+        });
+        JsonTrace {
+            recorder: recorder,
+        }
+    }
+    
+    /// Like `attach`, logging to a new file at `path`.
+    pub fn attach_to_file(c: &mut NodeCollection, path: &Path, prefixes: &[&str], snapshot_interval: u64) -> io::Result<JsonTrace> {
+        let file = try!(File::create(path));
+        Ok(JsonTrace::attach(c, Box::new(file), prefixes, snapshot_interval))
+    }
+    
+    /// Flushes the output, returning the first error writing to it.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut recorder = self.recorder.borrow_mut();
+        match recorder.error.take() {
+            Some(error) => Err(error),
+            None => recorder.output.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::io;
+    use std::io::Write;
+    use serialize::json;
+    
+    use sim::{NodeCollection, NodeCreator, LineState};
+    use logic_gates::NotGate;
+    use pin::Pin;
+    use super::JsonTrace;
+    
+    // Output that can still be read after it is handed to a trace
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+    
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn test_trace() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let pin = Pin::new(&mut creator);
+        let not = NotGate::new(&mut creator);
+        creator.wire(pin.node, not.input);
+        c.absorb(creator);
+        
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        let trace = JsonTrace::attach(&mut c, Box::new(SharedBuffer(buffer.clone())), &["NotGate0/Nand0.a", "NotGate0/Nand0.output"], 50);
+        // Lines are written as they are produced, starting with the first snapshot
+        assert!(buffer.borrow().ends_with(b"}}\n"));
+        pin.node.write(LineState::High, &mut c);
+        while c.play() {}
+        assert!(trace.finish().is_ok());
+        
+        let text = String::from_utf8(buffer.borrow().clone()).unwrap();
+        let lines : Vec<&str> = text.lines().collect();
+        for line in lines.iter() {
+            assert!(json::from_str(*line).is_ok(), "not JSON: {}", line);
+        }
+        assert_eq!(lines[0], "{\"snapshot\":0,\"states\":{\"NotGate0/Nand0.a\":\"0\",\"NotGate0/Nand0.output\":\"0\"}}");
+        assert!(lines.contains(&"{\"tick\":100,\"node\":\"NotGate0/Nand0.a\",\"state\":\"1\",\"forcer\":\"Pin0.node\"}"));
+        // The NAND's output rose while only its first input was high; the second follows at 200
+        assert!(lines.contains(&"{\"snapshot\":150,\"states\":{\"NotGate0/Nand0.a\":\"1\",\"NotGate0/Nand0.output\":\"1\"}}"));
+        // The first event, at 100, crossed two boundaries
+        assert_eq!(lines[1], "{\"snapshot\":50,\"states\":{\"NotGate0/Nand0.a\":\"0\",\"NotGate0/Nand0.output\":\"0\"}}");
+        assert_eq!(lines[2], "{\"snapshot\":100,\"states\":{\"NotGate0/Nand0.a\":\"0\",\"NotGate0/Nand0.output\":\"0\"}}");
+        // Only the chosen nodes are logged
+        assert!(lines.iter().all(|line| { !line.contains("\"node\":\"Pin0.node\"") && !line.contains("\"node\":\"NotGate0/Nand0.b\"") }));
+    }
+}
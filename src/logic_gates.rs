@@ -1,5 +1,6 @@
-use sim::{NodeIndex, NodeCreator, PropogationDelay, STANDARD_DELAY};
+use sim::{NodeIndex, NodeCreator, PropogationDelay};
 use nand::NandElem;
+use component::Component;
 
 use arena::Arena;
 
@@ -23,26 +24,18 @@ impl NandGate {
     }
 }
 
-#[derive(Debug)]
-pub struct AndGate {
-    pub a: NodeIndex,
-    pub b: NodeIndex,
-    pub output: NodeIndex,
+impl Component for NandGate {
+    fn ports(&self) -> Vec<(&'static str, NodeIndex)> {
+        vec![("a", self.a), ("b", self.b), ("output", self.output)]
+    }
 }
 
-impl AndGate {
-    pub fn new(creator: &mut NodeCreator) -> AndGate {
-        let nander = NandGate::new(creator);
-        let notter = NandGate::new(creator);
-        
-        creator.link(nander.output, notter.a, STANDARD_DELAY);
-        creator.link(nander.output, notter.b, STANDARD_DELAY);
-        
-        AndGate {
-            a: nander.a,
-            b: nander.b,
-            output: notter.output,
-        }
+component! {
+    #[derive(Debug)]
+    pub struct AndGate {
+        parts { nander: NandGate, notter: NandGate }
+        wires { nander.output -- notter.a, nander.output -- notter.b }
+        ports { a: nander.a, b: nander.b, output: notter.output }
     }
 }
 
@@ -53,7 +46,9 @@ pub struct AndGateVec {
 }
 impl AndGateVec {
     pub fn new(count: usize, creator: &mut NodeCreator) -> AndGateVec {
+        creator.begin_component("AndGateVec");
         let subgates : Vec<AndGate> = range(0, count).map(|_| { AndGate::new(creator) }).collect();
+        creator.end_component();
         AndGateVec {
             a: subgates.iter().map(|gate| { gate.a }).collect(),
             b: subgates.iter().map(|gate| { gate.b }).collect(),
@@ -62,31 +57,24 @@ impl AndGateVec {
     }
 }
 
-pub struct NotGate {
-    pub input: NodeIndex,
-    pub output: NodeIndex,
-}
-
-impl NotGate {
-    pub fn new(creator: &mut NodeCreator) -> NotGate {
-        let nand = NandGate::new(creator);
-        creator.link(nand.a, nand.b, STANDARD_DELAY);
-        NotGate {
-            input: nand.a,
-            output: nand.output
-        }
+component! {
+    pub struct NotGate {
+        parts { nand: NandGate }
+        wires { nand.a -- nand.b }
+        ports { input: nand.a, output: nand.output }
     }
 }
 
 #[allow(dead_code)]
 pub struct NWayAnd {
-    inputs: Vec<NodeIndex>,
-    output: NodeIndex,
+    pub inputs: Vec<NodeIndex>,
+    pub output: NodeIndex,
 }
 
 #[allow(dead_code)]
 impl NWayAnd {
     pub fn new(creator: &mut NodeCreator, input_count: usize) -> NWayAnd {
+        creator.begin_component("NWayAnd");
         if input_count < 2 {
             panic!("NWayAnd needs at least 2 inputs");
         }
@@ -100,11 +88,12 @@ impl NWayAnd {
         
         for _ in range(2, input_count) {
             let and = AndGate::new(creator);
-            creator.link(output_so_far, and.a, STANDARD_DELAY);
+            creator.wire(output_so_far, and.a);
             output_so_far = and.output;
             inputs.push(and.b);
         }
         
+        creator.end_component();
         NWayAnd {
             inputs: inputs,
             output: output_so_far
@@ -113,12 +102,14 @@ impl NWayAnd {
     
     
     pub fn new_logtime(creator: &mut NodeCreator, input_count: usize) -> NWayAnd {
+        creator.begin_component("NWayAnd");
         if input_count == 0 {
             panic!("Can't have an NWayAnd with no inputs!");
         }
          
-        let inputs : Vec<NodeIndex> = range(0, input_count).map(|_| { creator.new_node() }).collect();
-        let mut frontier : Vec<(NodeIndex, PropogationDelay)> = inputs.iter().map(|input| { (*input, PropogationDelay(0)) }).collect();
+        let inputs : Vec<NodeIndex> = range(0, input_count).map(|index| { creator.new_named_node(&format!("input{}", index)[]) }).collect();
+        // The inputs are the only thing on their nodes, so they link to their first gate with no delay.
+        let mut frontier : Vec<(NodeIndex, bool)> = inputs.iter().map(|input| { (*input, true) }).collect();
         while frontier.len() > 1 {
             println!("{:?}", frontier);
             let mut next_frontier = Vec::new();
@@ -126,11 +117,9 @@ impl NWayAnd {
             for pair in frontier.as_slice().chunks(2) {
                 if pair.len() == 2 {
                     let and = AndGate::new(creator);
-                    let (node_a, delay_a) = pair[0];
-                    let (node_b, delay_b) = pair[1];
-                    creator.link(node_a, and.a, delay_a);
-                    creator.link(node_b, and.b, delay_b);
-                    next_frontier.push((and.output, STANDARD_DELAY));
+                    link_frontier(creator, pair[0], and.a);
+                    link_frontier(creator, pair[1], and.b);
+                    next_frontier.push((and.output, false));
                 } else {
                     next_frontier.push(pair[0]);
                 }
@@ -141,6 +130,7 @@ impl NWayAnd {
         
         let (last_node, _) = frontier[0];
         
+        creator.end_component();
         NWayAnd {
             inputs: inputs,
             output: last_node
@@ -148,32 +138,26 @@ impl NWayAnd {
     }
 }
 
-
-pub struct XorGate {
-    pub a: NodeIndex,
-    pub b: NodeIndex,
-    pub output: NodeIndex,
+fn link_frontier(creator: &mut NodeCreator, (node, is_input): (NodeIndex, bool), to: NodeIndex) {
+    if is_input {
+        creator.link(node, to, PropogationDelay(0));
+    } else {
+        creator.wire(node, to);
+    }
 }
 
-impl XorGate {
-    pub fn new(creator: &mut NodeCreator) -> XorGate {
-        let a_nand_b = NandGate::new(creator);
-        let top = NandGate::new(creator);
-        let bottom = NandGate::new(creator);
-        let output = NandGate::new(creator);
-        
-        creator.link(a_nand_b.output, top.b, STANDARD_DELAY);
-        creator.link(a_nand_b.output, bottom.a, STANDARD_DELAY);
-        creator.link(a_nand_b.a, top.a, STANDARD_DELAY);
-        creator.link(a_nand_b.b, bottom.b, STANDARD_DELAY);
-        creator.link(top.output, output.a, STANDARD_DELAY);
-        creator.link(bottom.output, output.b, STANDARD_DELAY);
-        
-        XorGate {
-            a: a_nand_b.a,
-            b: a_nand_b.b,
-            output: output.output,
+component! {
+    pub struct XorGate {
+        parts { a_nand_b: NandGate, top: NandGate, bottom: NandGate, output: NandGate }
+        wires {
+            a_nand_b.output -- top.b,
+            a_nand_b.output -- bottom.a,
+            a_nand_b.a -- top.a,
+            a_nand_b.b -- bottom.b,
+            top.output -- output.a,
+            bottom.output -- output.b,
         }
+        ports { a: a_nand_b.a, b: a_nand_b.b, output: output.output }
     }
 }
 
@@ -184,7 +168,9 @@ pub struct XorGateVec {
 }
 impl XorGateVec {
     pub fn new(count: usize, creator: &mut NodeCreator) -> XorGateVec {
+        creator.begin_component("XorGateVec");
         let subgates : Vec<XorGate> = range(0, count).map(|_| { XorGate::new(creator) }).collect();
+        creator.end_component();
         XorGateVec {
             a: subgates.iter().map(|gate| { gate.a }).collect(),
             b: subgates.iter().map(|gate| { gate.b }).collect(),
@@ -193,27 +179,34 @@ impl XorGateVec {
     }
 }
 
-pub struct OrGate {
-    pub a: NodeIndex,
-    pub b: NodeIndex,
-    pub output: NodeIndex,
-}
-
-impl OrGate {
-    pub fn new(creator: &mut NodeCreator) -> OrGate {
-        let not_a = NandGate::new(creator);
-        let not_b = NandGate::new(creator);
-        let or = NandGate::new(creator);
-        
-        creator.link(not_a.a, not_a.b, STANDARD_DELAY);
-        creator.link(not_b.a, not_b.b, STANDARD_DELAY);
-        creator.link(not_a.output, or.a, STANDARD_DELAY);
-        creator.link(not_b.output, or.b, STANDARD_DELAY);
-        
-        OrGate {
-            a: not_a.a,
-            b: not_b.b,
-            output: or.output,
+component! {
+    pub struct OrGate {
+        parts { not_a: NandGate, not_b: NandGate, or: NandGate }
+        wires {
+            not_a.a -- not_a.b,
+            not_b.a -- not_b.b,
+            not_a.output -- or.a,
+            not_b.output -- or.b,
         }
+        ports { a: not_a.a, b: not_b.b, output: or.output }
     }
 }
+
+/// Feeds `a` and `b` into a new AND ('&'), OR ('|') or XOR (anything else) gate, returning its output.
+pub fn binary_gate(operator: char, a: NodeIndex, b: NodeIndex, creator: &mut NodeCreator) -> NodeIndex {
+    let (gate_a, gate_b, output) = match operator {
+        '&' => { let gate = AndGate::new(creator); (gate.a, gate.b, gate.output) },
+        '|' => { let gate = OrGate::new(creator); (gate.a, gate.b, gate.output) },
+        _ => { let gate = XorGate::new(creator); (gate.a, gate.b, gate.output) },
+    };
+    creator.wire(a, gate_a);
+    creator.wire(b, gate_b);
+    output
+}
+
+/// Feeds `node` into a new NOT gate, returning its output.
+pub fn invert(node: NodeIndex, creator: &mut NodeCreator) -> NodeIndex {
+    let not = NotGate::new(creator);
+    creator.wire(node, not.input);
+    not.output
+}
@@ -0,0 +1,845 @@
+use std::collections::HashMap;
+
+use sim::{NodeCreator, NodeIndex};
+use logic_gates::{binary_gate, invert};
+use storage::{DFlipFlop, Register};
+use rom::ConstantBits;
+
+// Just enough XML for Logisim's files: elements and attributes. Text, comments and
+// declarations are skipped.
+struct XmlElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|&&(ref key, _)| { &key[] == name }).map(|&(_, ref value)| { &value[] })
+    }
+    
+    fn children_named(&self, name: &str) -> Vec<&XmlElement> {
+        self.children.iter().filter(|child| { &child.name[] == name }).collect()
+    }
+    
+    // Logisim keeps a component's settings in <a name="..." val="..."/> children.
+    fn setting(&self, name: &str) -> Option<&str> {
+        for child in self.children.iter() {
+            if &child.name[] == "a" && child.attribute("name") == Some(name) {
+                return child.attribute("val");
+            }
+        }
+        None
+    }
+}
+
+struct XmlParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl XmlParser {
+    fn error(&self, message: &str) -> String {
+        let line = self.chars[..self.position].iter().filter(|c| { **c == '\n' }).count() + 1;
+        format!("line {}: {}", line, message)
+    }
+    
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).map(|c| { *c })
+    }
+    
+    fn starts_with(&self, prefix: &str) -> bool {
+        prefix.chars().enumerate().all(|(offset, c)| { self.chars.get(self.position + offset) == Some(&c) })
+    }
+    
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| { c.is_whitespace() }) {
+            self.position += 1;
+        }
+    }
+    
+    fn skip_past(&mut self, end: &str) -> Result<(), String> {
+        while !self.starts_with(end) {
+            if self.peek().is_none() {
+                return Err(self.error(&format!("missing {}", end)[]));
+            }
+            self.position += 1;
+        }
+        self.position += end.chars().count();
+        Ok(())
+    }
+    
+    // Skips text, comments, declarations and processing instructions up to the next tag.
+    fn skip_to_tag(&mut self) -> Result<(), String> {
+        loop {
+            while self.peek().map_or(false, |c| { c != '<' }) {
+                self.position += 1;
+            }
+            if self.starts_with("<!--") {
+                try!(self.skip_past("-->"));
+            } else if self.starts_with("<?") || self.starts_with("<!") {
+                try!(self.skip_past(">"));
+            } else {
+                return Ok(());
+            }
+        }
+    }
+    
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected {}", expected)[]));
+        }
+        self.position += 1;
+        Ok(())
+    }
+    
+    fn name(&mut self) -> Result<String, String> {
+        let start = self.position;
+        while self.peek().map_or(false, |c| { c.is_alphanumeric() || c == '_' || c == '-' || c == ':' || c == '.' }) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error("expected a name"));
+        }
+        Ok(self.chars[start..self.position].iter().map(|c| { *c }).collect())
+    }
+    
+    fn element(&mut self) -> Result<XmlElement, String> {
+        try!(self.expect('<'));
+        let name = try!(self.name());
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("/>") {
+                self.position += 2;
+                return Ok(XmlElement { name: name, attributes: attributes, children: Vec::new() });
+            }
+            if self.peek() == Some('>') {
+                self.position += 1;
+                break;
+            }
+            let key = try!(self.name());
+            self.skip_whitespace();
+            try!(self.expect('='));
+            self.skip_whitespace();
+            let quote = match self.peek() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => { return Err(self.error("expected a quoted value")); }
+            };
+            self.position += 1;
+            let start = self.position;
+            while self.peek().map_or(false, |c| { c != quote }) {
+                self.position += 1;
+            }
+            if self.peek().is_none() {
+                return Err(self.error("unterminated value"));
+            }
+            let value : String = self.chars[start..self.position].iter().map(|c| { *c }).collect();
+            self.position += 1;
+            attributes.push((key, unescape(&value[])));
+        }
+        
+        let mut children = Vec::new();
+        loop {
+            try!(self.skip_to_tag());
+            if self.peek().is_none() {
+                return Err(self.error(&format!("<{}> is never closed", name)[]));
+            }
+            if self.starts_with("</") {
+                self.position += 2;
+                let closing = try!(self.name());
+                if closing != name {
+                    return Err(self.error(&format!("</{}> closes <{}>", closing, name)[]));
+                }
+                self.skip_whitespace();
+                try!(self.expect('>'));
+                return Ok(XmlElement { name: name, attributes: attributes, children: children });
+            }
+            children.push(try!(self.element()));
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+fn parse_xml(text: &str) -> Result<XmlElement, String> {
+    let mut parser = XmlParser { chars: text.chars().collect(), position: 0 };
+    try!(parser.skip_to_tag());
+    parser.element()
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn parse_point(text: &str) -> Result<Point, String> {
+    let coordinates : Vec<&str> = text.trim().trim_left_matches('(').trim_right_matches(')').split(',').collect();
+    if coordinates.len() == 2 {
+        if let (Ok(x), Ok(y)) = (coordinates[0].trim().parse::<i32>(), coordinates[1].trim().parse::<i32>()) {
+            return Ok(Point { x: x, y: y });
+        }
+    }
+    Err(format!("bad point {}", text))
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Facing {
+    East,
+    West,
+    North,
+    South,
+}
+
+fn facing(comp: &XmlElement) -> Result<Facing, String> {
+    match comp.setting("facing") {
+        None | Some("east") => Ok(Facing::East),
+        Some("west") => Ok(Facing::West),
+        Some("north") => Ok(Facing::North),
+        Some("south") => Ok(Facing::South),
+        Some(other) => Err(format!("bad facing {}", other)),
+    }
+}
+
+// A point `along` the direction a component faces from its location (negative is behind
+// it) and `across` it.
+fn place(loc: Point, facing: Facing, along: i32, across: i32) -> Point {
+    match facing {
+        Facing::East => Point { x: loc.x + along, y: loc.y + across },
+        Facing::West => Point { x: loc.x - along, y: loc.y + across },
+        Facing::North => Point { x: loc.x + across, y: loc.y - along },
+        Facing::South => Point { x: loc.x + across, y: loc.y + along },
+    }
+}
+
+fn number_setting(comp: &XmlElement, name: &str, default: u64) -> Result<u64, String> {
+    match comp.setting(name) {
+        None => Ok(default),
+        Some(text) if text.starts_with("0x") => {
+            let mut value = 0;
+            for digit in text[2..].chars() {
+                match digit.to_digit(16) {
+                    Some(digit) => {
+                        value = match value.checked_mul(16).and_then(|value| { value.checked_add(digit as u64) }) {
+                            Some(value) => value,
+                            None => { return Err(format!("{} {} does not fit in 64 bits", name, text)); }
+                        };
+                    },
+                    None => { return Err(format!("bad {} {}", name, text)); }
+                }
+            }
+            Ok(value)
+        },
+        Some(text) => text.parse::<u64>().map_err(|_| { format!("bad {} {}", name, text) }),
+    }
+}
+
+// Whether a flip-flop or register takes the falling edge of its clock. Level triggers are
+// not supported.
+fn falling_edge(comp: &XmlElement) -> Result<bool, String> {
+    match comp.setting("trigger") {
+        None | Some("rising") => Ok(false),
+        Some("falling") => Ok(true),
+        Some(trigger) => Err(format!("{} at {}: {} triggers are not supported", comp.attribute("name").unwrap_or(""), comp.attribute("loc").unwrap_or(""), trigger)),
+    }
+}
+
+// How far across a gate's body Logisim puts input `index`, from AbstractGate.getInputOffset.
+fn gate_input_offset(index: i32, inputs: i32, size: i32) -> i32 {
+    let (start, step, lower_even) = if inputs <= 3 {
+        if size < 40 {
+            (-5, 10, 10)
+        } else if size < 60 || inputs <= 2 {
+            (-10, 20, 20)
+        } else {
+            (-15, 30, 30)
+        }
+    } else if inputs == 4 && size >= 60 {
+        (-5, 20, 0)
+    } else {
+        (-5, 10, 10)
+    };
+    if inputs % 2 == 1 {
+        start * (inputs - 1) + step * index
+    } else {
+        start * inputs + step * index + if index >= inputs / 2 { lower_even } else { 0 }
+    }
+}
+
+// Which end each bit of a splitter's combined end goes to, when the file doesn't say:
+// consecutive runs of bits, with the first ends taking one more bit if they don't divide evenly.
+fn default_split(incoming: usize, fanout: usize) -> Vec<Option<usize>> {
+    let mut ends = Vec::new();
+    for end in range(0, fanout) {
+        let bits = incoming / fanout + if end < incoming % fanout { 1 } else { 0 };
+        for _ in range(0, bits) {
+            ends.push(Some(end));
+        }
+    }
+    ends
+}
+
+// Where a subcircuit's pins are relative to its location, following Logisim's default
+// appearance (DefaultAppearance.build): each pin sits on the side opposite the way it faces,
+// in order of position, and the location is the first pin on the east side, or failing that
+// the north, west or south side.
+fn appearance(pins: &[(Point, Facing)]) -> Vec<Point> {
+    let side_of = |facing: Facing| {
+        match facing {
+            Facing::East => Facing::West,
+            Facing::West => Facing::East,
+            Facing::North => Facing::South,
+            Facing::South => Facing::North,
+        }
+    };
+    let on_side = |side: Facing| {
+        let mut indices : Vec<usize> = range(0, pins.len()).filter(|index| { side_of(pins[*index].1) == side }).collect();
+        indices.sort_by(|a, b| {
+            let (a, b) = (pins[*a].0, pins[*b].0);
+            if side == Facing::East || side == Facing::West { (a.y, a.x).cmp(&(b.y, b.x)) } else { (a.x, a.y).cmp(&(b.x, b.y)) }
+        });
+        indices
+    };
+    let (north, south, east, west) = (on_side(Facing::North), on_side(Facing::South), on_side(Facing::East), on_side(Facing::West));
+    
+    let offset = |count: usize, opposite: usize, others: usize| {
+        let most = if count > opposite { count } else { opposite };
+        let first = match most {
+            0 | 1 => if others == 0 { 15 } else { 10 },
+            2 => 10,
+            _ => if others == 0 { 5 } else { 10 },
+        };
+        (first + 10 * ((most - count) / 2)) as i32
+    };
+    let dimension = |most: usize, others: usize| {
+        (if most < 3 { 30 } else if others == 0 { 10 * most } else { 10 * most + 10 }) as i32
+    };
+    let most_vertical = if north.len() > south.len() { north.len() } else { south.len() };
+    let most_horizontal = if east.len() > west.len() { east.len() } else { west.len() };
+    let (offset_north, offset_south) = (offset(north.len(), south.len(), most_horizontal), offset(south.len(), north.len(), most_horizontal));
+    let (offset_east, offset_west) = (offset(east.len(), west.len(), most_vertical), offset(west.len(), east.len(), most_vertical));
+    let width = dimension(most_vertical, most_horizontal);
+    let height = dimension(most_horizontal, most_vertical);
+    
+    let anchor = if east.len() > 0 {
+        Point { x: width, y: offset_east }
+    } else if north.len() > 0 {
+        Point { x: offset_north, y: 0 }
+    } else if west.len() > 0 {
+        Point { x: 0, y: offset_west }
+    } else {
+        Point { x: offset_south, y: height }
+    };
+    
+    let mut offsets : Vec<Point> = pins.iter().map(|_| { Point { x: 0, y: 0 } }).collect();
+    for (order, index) in west.iter().enumerate() {
+        offsets[*index] = Point { x: 0, y: offset_west + 10 * order as i32 };
+    }
+    for (order, index) in east.iter().enumerate() {
+        offsets[*index] = Point { x: width, y: offset_east + 10 * order as i32 };
+    }
+    for (order, index) in north.iter().enumerate() {
+        offsets[*index] = Point { x: offset_north + 10 * order as i32, y: 0 };
+    }
+    for (order, index) in south.iter().enumerate() {
+        offsets[*index] = Point { x: offset_south + 10 * order as i32, y: height };
+    }
+    offsets.iter().map(|point| { Point { x: point.x - anchor.x, y: point.y - anchor.y } }).collect()
+}
+
+enum Part {
+    Pin(Option<String>),
+    Constant(u64),
+    Tunnel(String),
+    Splitter(Vec<Option<usize>>), // The end each bit of the combined end goes to
+    Gate(char, bool), // The operator joining the inputs, and whether the output is inverted
+    Buffer(bool), // Whether it inverts
+    FlipFlop(bool), // Whether it takes the falling edge
+    Register(bool),
+    Subcircuit(String),
+    Decoration,
+}
+
+struct Component {
+    name: String,
+    loc: Point,
+    width: usize,
+    part: Part,
+    pins: Vec<(Point, usize)>, // Where each connection is, and its width
+}
+
+// A subcircuit's pins in the order they appear in the file: where each one is, which way it faces and its width.
+fn circuit_pins(circuit: &XmlElement) -> Result<Vec<(Point, Facing, usize)>, String> {
+    let mut pins = Vec::new();
+    for comp in circuit.children_named("comp").iter() {
+        if comp.attribute("name") == Some("Pin") {
+            let loc = try!(parse_point(comp.attribute("loc").unwrap_or("")));
+            pins.push((loc, try!(facing(*comp)), try!(number_setting(*comp, "width", 1)) as usize));
+        }
+    }
+    Ok(pins)
+}
+
+/// The pins of a circuit read from Logisim, by label. Pins without a label are left out.
+pub struct LogisimCircuit {
+    pub pins: HashMap<String, Vec<NodeIndex>>,
+}
+
+impl LogisimCircuit {
+    pub fn pin(&self, label: &str) -> Option<Vec<NodeIndex>> {
+        self.pins.get(label).map(|nodes| { nodes.clone() })
+    }
+}
+
+// The points of a circuit joined into nets by wires and tunnels, and the nodes of each net.
+struct Nets {
+    indices: HashMap<Point, usize>,
+    parents: Vec<usize>,
+    attachments: Vec<(Point, usize)>, // Every wire end and component connection, with its width
+    counts: HashMap<usize, usize>, // How many attachments each net has, once everything is joined
+    nodes: HashMap<usize, Vec<NodeIndex>>,
+}
+
+impl Nets {
+    fn root(&mut self, point: Point) -> usize {
+        let mut index = match self.indices.get(&point) {
+            Some(index) => *index,
+            None => {
+                let index = self.parents.len();
+                self.parents.push(index);
+                index
+            }
+        };
+        self.indices.insert(point, index);
+        while self.parents[index] != index {
+            index = self.parents[index];
+        }
+        index
+    }
+    
+    fn join(&mut self, a: Point, b: Point) {
+        let (a, b) = (self.root(a), self.root(b));
+        self.parents[b] = a;
+    }
+    
+    // Counts the attachments on each net, for `connected`. Call once everything is joined.
+    fn count_attachments(&mut self) {
+        for &(point, _) in self.attachments.clone().iter() {
+            let root = self.root(point);
+            let count = *self.counts.get(&root).unwrap_or(&0);
+            self.counts.insert(root, count + 1);
+        }
+    }
+    
+    // Whether anything besides the one connection at this point is on its net.
+    fn connected(&mut self, point: Point) -> bool {
+        let root = self.root(point);
+        *self.counts.get(&root).unwrap_or(&0) > 1
+    }
+    
+    // The width of the net at each point, checking everything on a net agrees.
+    fn check_widths(&mut self) -> Result<HashMap<usize, usize>, String> {
+        let mut widths = HashMap::new();
+        for &(point, width) in self.attachments.clone().iter() {
+            if width == 0 {
+                continue;
+            }
+            let root = self.root(point);
+            if let Some(existing) = widths.insert(root, width) {
+                if existing != width {
+                    return Err(format!("{} bits meet {} bits at ({},{})", width, existing, point.x, point.y));
+                }
+            }
+        }
+        Ok(widths)
+    }
+    
+    fn nodes(&mut self, point: Point, width: usize, names: Option<&str>, creator: &mut NodeCreator) -> Vec<NodeIndex> {
+        let root = self.root(point);
+        if let Some(nodes) = self.nodes.get(&root) {
+            return nodes.clone();
+        }
+        let nodes : Vec<NodeIndex> = range(0, width).map(|bit| {
+            match names {
+                Some(name) if width == 1 => creator.new_named_node(name),
+                Some(name) => creator.new_named_node(&format!("{}[{}]", name, bit)[]),
+                None => creator.new_node(),
+            }
+        }).collect();
+        self.nodes.insert(root, nodes.clone());
+        nodes
+    }
+}
+
+struct Importer<'x> {
+    circuits: HashMap<String, &'x XmlElement>,
+    building: Vec<String>,
+}
+
+impl<'x> Importer<'x> {
+    fn component(&self, comp: &XmlElement) -> Result<Component, String> {
+        let name = comp.attribute("name").unwrap_or("").to_string();
+        let loc = try!(parse_point(comp.attribute("loc").unwrap_or("")));
+        let width = try!(number_setting(comp, "width", if &name[] == "Register" { 8 } else { 1 })) as usize;
+        let at = |offset_x: i32, offset_y: i32| { Point { x: loc.x + offset_x, y: loc.y + offset_y } };
+        
+        let gate = match &name[] {
+            "AND Gate" => Some(('&', false, 0)),
+            "OR Gate" => Some(('|', false, 0)),
+            "XOR Gate" => Some(('^', false, 10)),
+            "NAND Gate" => Some(('&', true, 10)),
+            "NOR Gate" => Some(('|', true, 10)),
+            "XNOR Gate" => Some(('^', true, 20)),
+            _ => None,
+        };
+        let (part, pins) = if let Some((operator, negated, extra_length)) = gate {
+            let facing = try!(facing(comp));
+            let size = try!(number_setting(comp, "size", 50)) as i32;
+            let inputs = try!(number_setting(comp, "inputs", 5)) as i32;
+            let mut pins = vec![(loc, width)];
+            for index in range(0, inputs) {
+                pins.push((place(loc, facing, -(size + extra_length), gate_input_offset(index, inputs, size)), width));
+            }
+            (Part::Gate(operator, negated), pins)
+        } else {
+            match &name[] {
+                "Pin" => {
+                    (Part::Pin(comp.setting("label").map(|label| { label.to_string() })), vec![(loc, width)])
+                },
+                "Constant" => (Part::Constant(try!(number_setting(comp, "value", 1))), vec![(loc, width)]),
+                "Tunnel" => {
+                    let label = try!(comp.setting("label").ok_or(format!("Tunnel at ({},{}) has no label", loc.x, loc.y)));
+                    (Part::Tunnel(label.to_string()), vec![(loc, width)])
+                },
+                "Splitter" => {
+                    let fanout = try!(number_setting(comp, "fanout", 2)) as usize;
+                    let incoming = try!(number_setting(comp, "incoming", 2)) as usize;
+                    if fanout == 0 {
+                        return Err(format!("Splitter at ({},{}) has no ends", loc.x, loc.y));
+                    }
+                    if try!(facing(comp)) != Facing::East {
+                        return Err(format!("Splitter at ({},{}): only splitters facing east are supported", loc.x, loc.y));
+                    }
+                    // Left-handed splitters fan out above the combined end, right-handed ones below it
+                    let first_end = match comp.setting("appear") {
+                        None | Some("left") => -10 * fanout as i32,
+                        Some("right") => 10,
+                        Some(other) => { return Err(format!("Splitter at ({},{}): {} appearance is not supported", loc.x, loc.y, other)); }
+                    };
+                    let mut ends = default_split(incoming, fanout);
+                    for bit in range(0, incoming) {
+                        match comp.setting(&format!("bit{}", bit)[]) {
+                            None => {},
+                            Some("none") => { ends[bit] = None; },
+                            Some(end) => {
+                                ends[bit] = match end.parse::<usize>() {
+                                    Ok(end) if end < fanout => Some(end),
+                                    _ => { return Err(format!("Splitter at ({},{}): bad end {}", loc.x, loc.y, end)); }
+                                };
+                            }
+                        }
+                    }
+                    let mut pins = vec![(loc, incoming)];
+                    for end in range(0, fanout) {
+                        let bits = ends.iter().filter(|other| { **other == Some(end) }).count();
+                        pins.push((at(20, first_end + 10 * end as i32), bits));
+                    }
+                    (Part::Splitter(ends), pins)
+                },
+                "NOT Gate" | "Buffer" => {
+                    let length = if &name[] == "Buffer" { 20 } else { try!(number_setting(comp, "size", 30)) as i32 };
+                    (Part::Buffer(&name[] == "NOT Gate"), vec![(loc, width), (place(loc, try!(facing(comp)), -length, 0), width)])
+                },
+                "D Flip-Flop" => {
+                    // Data, clock, q and not q
+                    (Part::FlipFlop(try!(falling_edge(comp))), vec![(at(-40, 0), 1), (at(-40, 20), 1), (loc, 1), (at(0, 20), 1)])
+                },
+                "Register" => {
+                    // Output, input and clock; clear and enable are left unconnected
+                    (Part::Register(try!(falling_edge(comp))), vec![(loc, width), (at(-30, 0), width), (at(-20, 20), 1)])
+                },
+                "Text" => (Part::Decoration, Vec::new()),
+                _ => {
+                    let circuit = match (comp.attribute("lib"), self.circuits.get(&name)) {
+                        (None, Some(circuit)) => *circuit,
+                        _ => { return Err(format!("{} at ({},{}) is not supported", name, loc.x, loc.y)); }
+                    };
+                    if try!(facing(comp)) != Facing::East {
+                        return Err(format!("{} at ({},{}): only subcircuits facing east are supported", name, loc.x, loc.y));
+                    }
+                    let child_pins = try!(circuit_pins(circuit));
+                    let placement : Vec<(Point, Facing)> = child_pins.iter().map(|&(point, facing, _)| { (point, facing) }).collect();
+                    let offsets = appearance(&placement[]);
+                    let pins = offsets.iter().zip(child_pins.iter()).map(|(offset, &(_, _, width))| { (at(offset.x, offset.y), width) }).collect();
+                    (Part::Subcircuit(name.clone()), pins)
+                },
+            }
+        };
+        
+        Ok(Component { name: name, loc: loc, width: width, part: part, pins: pins })
+    }
+    
+    // Builds a circuit, returning the nodes of its pins in the order they appear in the file.
+    fn build(&mut self, name: &str, creator: &mut NodeCreator) -> Result<Vec<(Option<String>, Vec<NodeIndex>)>, String> {
+        if self.building.iter().any(|outer| { &outer[] == name }) {
+            return Err(format!("circuit {} contains itself", name));
+        }
+        let circuit = match self.circuits.get(name) {
+            Some(circuit) => *circuit,
+            None => { return Err(format!("no circuit named {}", name)); }
+        };
+        
+        self.building.push(name.to_string());
+        creator.begin_component(name);
+        let pins = self.build_circuit(circuit, creator);
+        creator.end_component();
+        self.building.pop();
+        pins.map_err(|error| { format!("circuit {}: {}", name, error) })
+    }
+    
+    fn build_circuit(&mut self, circuit: &XmlElement, creator: &mut NodeCreator) -> Result<Vec<(Option<String>, Vec<NodeIndex>)>, String> {
+        let mut components = Vec::new();
+        for comp in circuit.children_named("comp").iter() {
+            components.push(try!(self.component(*comp)));
+        }
+        
+        let mut nets = Nets { indices: HashMap::new(), parents: Vec::new(), attachments: Vec::new(), counts: HashMap::new(), nodes: HashMap::new() };
+        for wire in circuit.children_named("wire").iter() {
+            let from = try!(parse_point(wire.attribute("from").unwrap_or("")));
+            let to = try!(parse_point(wire.attribute("to").unwrap_or("")));
+            nets.join(from, to);
+            nets.attachments.push((from, 0));
+            nets.attachments.push((to, 0));
+        }
+        let mut tunnels : HashMap<String, Point> = HashMap::new();
+        for component in components.iter() {
+            if let Part::Tunnel(ref label) = component.part {
+                match tunnels.get(label) {
+                    Some(first) => { nets.join(*first, component.loc); },
+                    None => {},
+                }
+                if !tunnels.contains_key(label) {
+                    tunnels.insert(label.clone(), component.loc);
+                }
+            }
+            nets.attachments.push_all(&component.pins[]);
+        }
+        let widths = try!(nets.check_widths());
+        nets.count_attachments();
+        
+        // Labelled pins name their nets
+        for component in components.iter() {
+            if let Part::Pin(Some(ref label)) = component.part {
+                nets.nodes(component.loc, component.width, Some(&label[]), creator);
+            }
+        }
+        
+        let mut pins = Vec::new();
+        for component in components.iter() {
+            let mut connections = Vec::new();
+            for &(point, _) in component.pins.iter() {
+                let root = nets.root(point);
+                let width = *widths.get(&root).unwrap_or(&0);
+                connections.push(nets.nodes(point, width, None, creator));
+            }
+            
+            match component.part {
+                Part::Pin(ref label) => {
+                    pins.push((label.clone(), connections[0].clone()));
+                },
+                Part::Constant(value) => {
+                    let bits = ConstantBits::new(&ConstantBits::make_bits(value, component.width)[], creator);
+                    creator.multiwire(&bits.bits[], &connections[0][]);
+                },
+                Part::Splitter(ref ends) => {
+                    let mut used : Vec<usize> = connections.iter().map(|_| { 0 }).collect();
+                    for (bit, end) in ends.iter().enumerate() {
+                        if let Some(end) = *end {
+                            creator.wire(connections[0][bit], connections[end + 1][used[end]]);
+                            used[end] += 1;
+                        }
+                    }
+                },
+                Part::Gate(operator, negated) => {
+                    // Like Logisim, ignore inputs nothing is connected to
+                    let mut inputs = Vec::new();
+                    for (index, &(point, _)) in component.pins.iter().enumerate().skip(1) {
+                        if nets.connected(point) {
+                            inputs.push(connections[index].clone());
+                        }
+                    }
+                    if inputs.is_empty() {
+                        return Err(format!("{} at ({},{}) has no inputs connected", component.name, component.loc.x, component.loc.y));
+                    }
+                    for bit in range(0, component.width) {
+                        // Several inputs of an XOR gate give their parity
+                        let mut output = inputs[0][bit];
+                        for input in inputs[1..].iter() {
+                            output = binary_gate(operator, output, input[bit], creator);
+                        }
+                        if negated {
+                            output = invert(output, creator);
+                        }
+                        creator.wire(output, connections[0][bit]);
+                    }
+                },
+                Part::Buffer(negated) => {
+                    for bit in range(0, component.width) {
+                        let output = if negated { invert(connections[1][bit], creator) } else { connections[1][bit] };
+                        creator.wire(output, connections[0][bit]);
+                    }
+                },
+                Part::FlipFlop(falling) => {
+                    let flip_flop = DFlipFlop::new(creator);
+                    let clock = if falling { invert(connections[1][0], creator) } else { connections[1][0] };
+                    creator.wire(connections[0][0], flip_flop.data);
+                    creator.wire(clock, flip_flop.clock);
+                    creator.wire(flip_flop.q, connections[2][0]);
+                    creator.wire(flip_flop.not_q, connections[3][0]);
+                },
+                Part::Register(falling) => {
+                    let register = Register::new(creator, component.width);
+                    let clock = if falling { invert(connections[2][0], creator) } else { connections[2][0] };
+                    creator.multiwire(&register.outputs[], &connections[0][]);
+                    creator.multiwire(&connections[1][], &register.inputs[]);
+                    creator.wire(clock, register.clock);
+                },
+                Part::Subcircuit(ref name) => {
+                    let child_pins = try!(self.build(&name[], creator));
+                    for (connection, &(_, ref nodes)) in connections.iter().zip(child_pins.iter()) {
+                        creator.multiwire(&connection[], &nodes[]);
+                    }
+                },
+                Part::Tunnel(_) | Part::Decoration => {},
+            }
+        }
+        Ok(pins)
+    }
+}
+
+/// Builds the main circuit of a Logisim project.
+pub fn read_logisim(text: &str, creator: &mut NodeCreator) -> Result<LogisimCircuit, String> {
+    let project = try!(parse_xml(text));
+    let main = project.children.iter().find(|child| { &child.name[] == "main" })
+        .or(project.children.iter().find(|child| { &child.name[] == "circuit" }))
+        .and_then(|element| { element.attribute("name") });
+    match main {
+        Some(main) => read_logisim_project(&project, main, creator),
+        None => Err("the project has no circuits".to_string()),
+    }
+}
+
+/// Builds the circuit called `name` from a Logisim project. Gates, pins, constants,
+/// splitters, tunnels, D flip-flops, registers and subcircuits are supported, laid out as
+/// Logisim 2.7 draws them; subcircuits must use the default appearance. Flip-flops and
+/// registers start out unknown, must be edge triggered, and their set, reset, clear and
+/// enable inputs are ignored.
+pub fn read_logisim_circuit(text: &str, name: &str, creator: &mut NodeCreator) -> Result<LogisimCircuit, String> {
+    let project = try!(parse_xml(text));
+    read_logisim_project(&project, name, creator)
+}
+
+fn read_logisim_project(project: &XmlElement, name: &str, creator: &mut NodeCreator) -> Result<LogisimCircuit, String> {
+    if &project.name[] != "project" {
+        return Err("not a Logisim project".to_string());
+    }
+    let mut importer = Importer { circuits: HashMap::new(), building: Vec::new() };
+    for circuit in project.children_named("circuit").iter() {
+        if let Some(circuit_name) = circuit.attribute("name") {
+            importer.circuits.insert(circuit_name.to_string(), *circuit);
+        }
+    }
+    
+    let pins = try!(importer.build(name, creator));
+    let mut labelled = HashMap::new();
+    for (label, nodes) in pins.into_iter() {
+        if let Some(label) = label {
+            labelled.insert(label, nodes);
+        }
+    }
+    Ok(LogisimCircuit { pins: labelled })
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay, read_bus, bus_states};
+    use truth_table::check_truth_table;
+    use pin::Pin;
+    use super::{read_logisim, read_logisim_circuit};
+    
+    #[test]
+    fn test_half_adder() {
+        // The AND gate keeps Logisim's default of five inputs, only two of them connected
+        check_truth_table(|creator: &mut NodeCreator| {
+            let circuit = read_logisim(include_str!("../fixtures/logisim/half_adder.circ"), creator).unwrap();
+            let pin = |label: &str| { circuit.pin(label).unwrap()[0] };
+            (vec![pin("a"), pin("b")], vec![pin("sum"), pin("carry")])
+        }, &[
+            (&[0,0], &[0,0]),
+            (&[1,0], &[1,0]),
+            (&[0,1], &[1,0]),
+            (&[1,1], &[0,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_subcircuits_and_tunnels() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let circuit = read_logisim(include_str!("../fixtures/logisim/full_adder.circ"), creator).unwrap();
+            let pin = |label: &str| { circuit.pin(label).unwrap()[0] };
+            (vec![pin("a"), pin("b"), pin("cin")], vec![pin("s"), pin("cout")])
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,0,0], &[1,0]),
+            (&[1,1,0], &[0,1]),
+            (&[0,0,1], &[1,0]),
+            (&[0,1,1], &[0,1]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_register_splitter_and_flip_flop() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let circuit = read_logisim(include_str!("../fixtures/logisim/register.circ"), &mut creator).unwrap();
+        let data : Vec<Pin> = range(0, 4).map(|_| { Pin::new(&mut creator) }).collect();
+        let clock = Pin::new(&mut creator);
+        for (pin, node) in data.iter().zip(circuit.pin("d").unwrap().iter()) {
+            creator.wire(pin.node, *node);
+        }
+        creator.wire(clock.node, circuit.pin("clk").unwrap()[0]);
+        c.absorb(creator);
+        
+        clock.node.write(LineState::Low, &mut c);
+        for (pin, state) in data.iter().zip(bus_states(9, 4).into_iter()) {
+            pin.node.write(state, &mut c);
+        }
+        clock.node.write_later(LineState::High, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!(read_bus(&circuit.pin("low").unwrap()[], &c), Some(1));
+        assert_eq!(read_bus(&circuit.pin("high").unwrap()[], &c), Some(2));
+        assert_eq!(circuit.pin("ready").unwrap()[0].read(&c), LineState::High);
+    }
+    
+    #[test]
+    fn test_errors() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        assert_eq!(read_logisim("<project><circuit name=\"main\">\n<comp lib=\"2\" loc=\"(10,10)\" name=\"Multiplexer\"/>\n</circuit></project>", &mut creator).err(),
+            Some("circuit main: Multiplexer at (10,10) is not supported".to_string()));
+        assert_eq!(read_logisim_circuit("<project><circuit name=\"loop\"><comp loc=\"(50,50)\" name=\"loop\"/></circuit></project>", "loop", &mut creator).err(),
+            Some("circuit loop: circuit loop contains itself".to_string()));
+        assert_eq!(read_logisim("<project>\n<circuit name=\"main\">\n</project>", &mut creator).err(),
+            Some("line 3: </project> closes <circuit>".to_string()));
+        assert_eq!(read_logisim("<project><circuit name=\"main\">\n<comp lib=\"4\" loc=\"(50,50)\" name=\"D Flip-Flop\"><a name=\"trigger\" val=\"high\"/></comp>\n</circuit></project>", &mut creator).err(),
+            Some("circuit main: D Flip-Flop at (50,50): high triggers are not supported".to_string()));
+        assert_eq!(read_logisim("<project><circuit name=\"main\">\n<comp lib=\"0\" loc=\"(50,50)\" name=\"Splitter\"><a name=\"fanout\" val=\"0\"/></comp>\n</circuit></project>", &mut creator).err(),
+            Some("circuit main: Splitter at (50,50) has no ends".to_string()));
+        assert_eq!(read_logisim("<project><circuit name=\"main\">\n<comp lib=\"0\" loc=\"(50,50)\" name=\"Constant\"><a name=\"value\" val=\"0x10000000000000000\"/></comp>\n</circuit></project>", &mut creator).err(),
+            Some("circuit main: value 0x10000000000000000 does not fit in 64 bits".to_string()));
+    }
+}
@@ -1,8 +1,12 @@
 #![feature(core, rustc_private)]
 
 extern crate arena;
+extern crate serialize;
+extern crate time;
 
 mod sim;
+#[macro_use]
+mod component;
 mod storage;
 mod logic_gates;
 mod pin;
@@ -11,6 +15,25 @@ mod adder;
 mod rom;
 mod mux;
 mod demux;
+mod clock;
+mod stimulus;
+mod settle;
+mod jitter;
+mod stats;
+mod coverage;
+mod fault;
+mod debugger;
+mod primitive;
+mod snapshot;
+mod netlist;
+mod verilog;
+mod yosys;
+mod blif;
+mod logisim;
+mod dot;
+mod waveform;
+mod json_trace;
+mod hdl;
 
 mod cpu0;
 
@@ -19,9 +42,10 @@ mod truth_table;
 
 use pin::Pin;
 
-use sim::{LineState, NodeIndex, NodeCreator, Element, NodeCollection, PropogationDelay, STANDARD_DELAY};
+use sim::{LineState, NodeIndex, NodeCreator, Element, NodeCollection};
 use storage::{Register};
 use rom::{ConstantBit, ConstantBits};
+use clock::{Clock, ClockParams};
 
 
 fn main() {
@@ -30,16 +54,21 @@ fn main() {
     let power = ConstantBit::new(true, &mut creator);
     let ground = Pin::new(&mut creator);
     let overall_output = Pin::new(&mut creator);
-    let clock = Pin::new(&mut creator);
+    let clock = Clock::new(ClockParams{
+        period: 2000,
+        high_ticks: 1000,
+        phase: 1000,
+        stop: Some(3000),
+    }, &mut creator);
     let data = Pin::new(&mut creator);
     
     let forty_two = ConstantBits::new(&ConstantBits::make_bits(42, 8)[], &mut creator);
     
     let r = Register::new(&mut creator, 8);
-    creator.link(clock.node, r.clock, STANDARD_DELAY);
-    //creator.link(power.node, r.bits[0].data, STANDARD_DELAY);
+    creator.wire(clock.node, r.clock);
+    //creator.wire(power.node, r.bits[0].data);
     
-    creator.multilink(&forty_two.bits[], &r.inputs[], STANDARD_DELAY);
+    creator.multiwire(&forty_two.bits[], &r.inputs[]);
     
     
     c.absorb(creator);
@@ -47,17 +76,16 @@ fn main() {
     //power.node.write(LineState::High, &mut c);
     ground.node.write(LineState::Low, &mut c);
     data.node.write(LineState::Low, &mut c);
-    clock.node.write(LineState::Low, &mut c);
     
-    clock.node.write_later(LineState::High, PropogationDelay(1000), &mut c);
-    clock.node.write_later(LineState::Low, PropogationDelay(2000), &mut c);
-    
-    loop {
-        let more = c.play();
-        if !more { break; }
+    if std::env::args().nth(1) == Some("debug".to_string()) {
+        debugger::repl(&mut c);
+        return;
     }
     
+    let stats = stats::play_with_stats(&mut c);
+    
     println!("r = {:?}", r.read_u64(&c));
     println!("power = {:?}", power.node.read(&c));
     println!("exited at t={}", c.current_tick);
+    print!("{}", stats.report());
 }
\ No newline at end of file
@@ -1,26 +1,16 @@
-use sim::{NodeIndex, NodeCreator, STANDARD_DELAY};
+use sim::{NodeIndex, NodeCreator};
 use logic_gates::{AndGate, OrGate, NotGate};
 
-pub struct BitMux {
-    pub a: NodeIndex,
-    pub b: NodeIndex,
-    pub select: NodeIndex,
-    pub output: NodeIndex,
-}
-
-impl BitMux {
-    pub fn new(creator: &mut NodeCreator) -> BitMux {
-        let not_select = NotGate::new(creator);
-        let a_masked = AndGate::new(creator);
-        let b_masked = AndGate::new(creator);
-        let output = OrGate::new(creator);
-        
-        creator.link(not_select.output, a_masked.a, STANDARD_DELAY);
-        creator.link(not_select.input, b_masked.a, STANDARD_DELAY);
-        creator.link(a_masked.output, output.a, STANDARD_DELAY);
-        creator.link(b_masked.output, output.b, STANDARD_DELAY);
-        
-        BitMux {
+component! {
+    pub struct BitMux {
+        parts { not_select: NotGate, a_masked: AndGate, b_masked: AndGate, output: OrGate }
+        wires {
+            not_select.output -- a_masked.a,
+            not_select.input -- b_masked.a,
+            a_masked.output -- output.a,
+            b_masked.output -- output.b,
+        }
+        ports {
             a: a_masked.b,
             b: b_masked.b,
             select: not_select.input,
@@ -39,14 +29,16 @@ pub struct Mux {
 
 impl Mux {
     pub fn new(word_bits: usize, creator: &mut NodeCreator) -> Mux {
+        creator.begin_component("Mux");
         assert!(word_bits>0);
     
         let bits : Vec<BitMux> = range(0, word_bits).map(|_| { BitMux::new(creator) }).collect();
         
         for bit in (&bits[1..]).iter() {
-            creator.link(bits[0].select, bit.select, STANDARD_DELAY);
+            creator.wire(bits[0].select, bit.select);
         }
         
+        creator.end_component();
         Mux {
             a: bits.iter().map(|bit| { bit.a }).collect(),
             b: bits.iter().map(|bit| { bit.b }).collect(),
@@ -66,8 +58,9 @@ impl MuxN {
     pub fn new(word_bits: usize, word_count: usize, creator: &mut NodeCreator) -> MuxN {
         assert!(word_count >= 1);
         
-        if word_count == 1 {
-            let nodes : Vec<NodeIndex> = range(0, word_bits).map(|_| { creator.new_node() }).collect();
+        creator.begin_component("MuxN");
+        let mux = if word_count == 1 {
+            let nodes : Vec<NodeIndex> = range(0, word_bits).map(|index| { creator.new_named_node(&format!("bit{}", index)[]) }).collect();
             MuxN {
                 inputs: [nodes.clone()].to_vec(),
                 output: nodes.clone(),
@@ -82,9 +75,9 @@ impl MuxN {
             let mut lower = MuxN::new(word_bits, lower_size, creator);
             let mut upper = MuxN::new(word_bits, word_count - lower_size, creator);
             let top_level_chooser = Mux::new(word_bits, creator);
-            creator.multilink(&lower.output[], &top_level_chooser.a[], STANDARD_DELAY);
-            creator.multilink(&upper.output[], &top_level_chooser.b[], STANDARD_DELAY);
-            creator.multilink(&lower.select[..upper.select.len()], &upper.select[], STANDARD_DELAY);
+            creator.multiwire(&lower.output[], &top_level_chooser.a[]);
+            creator.multiwire(&upper.output[], &top_level_chooser.b[]);
+            creator.multiwire(&lower.select[..upper.select.len()], &upper.select[]);
             
             let mut select = lower.select;
             select.push(top_level_chooser.select);
@@ -97,7 +90,9 @@ impl MuxN {
                 output: top_level_chooser.output,
                 select: select,
             }
-        }
+        };
+        creator.end_component();
+        mux
     }
 }
 
@@ -1,19 +1,24 @@
-use sim::{LineState, NodeIndex, NodeCreator, Element, NodeCollection};
+use sim::{LineState, NodeIndex, NodeCreator, Element, NodeCollection, PropogationDelay};
 
 #[derive(Debug)]
 pub struct NandElem {
     pub a: NodeIndex,
     pub b: NodeIndex,
     pub output: NodeIndex,
+    pub delay: PropogationDelay,
 }
 
 impl NandElem {
     pub fn new(c: &mut NodeCreator) -> NandElem {
-        NandElem {
-            a: c.new_node(),
-            b: c.new_node(),
-            output: c.new_node(),
-        }
+        c.begin_component("Nand");
+        let elem = NandElem {
+            a: c.new_named_node("a"),
+            b: c.new_named_node("b"),
+            output: c.new_named_node("output"),
+            delay: c.delays.nand,
+        };
+        c.end_component();
+        elem
     }
 }
 
@@ -29,7 +34,7 @@ impl Element for NandElem {
             _ => LineState::High
         };
         //println!("Running nand {:?}: {:?} {:?} -> {:?}", self, self.a.read(c), self.b.read(c), res);
-        self.output.write(res , c);
+        self.output.write_after(res, self.delay, c);
     }
     
     fn get_nodes(&self) -> Vec<NodeIndex> {
@@ -39,4 +44,12 @@ impl Element for NandElem {
         v.push(self.output);
         v
     }
+    
+    fn kind(&self) -> &'static str {
+        "Nand"
+    }
+    
+    fn params(&self) -> Vec<u64> {
+        vec![self.delay.0 as u64]
+    }
 }
@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+
+use sim::{NodeCreator, NodeIndex, PropogationDelay};
+use primitive::{primitive_node_count, rebuild_element};
+
+const HEADER: &'static str = "netlist 1";
+
+// A netlist is one statement per line, after a "netlist 1" header:
+//   <Kind> <node>... <param>...     a primitive element, such as "Nand x y out 0"
+//   wire <node> <node>              a link whose delay comes from the delay library
+//   link <node> <node> <delay>      a link with a fixed delay
+// A node is written as its name, or as #<number> if it has none. Each node belongs to at most
+// one element, and appears once in it. Lines starting with // are comments.
+
+/// The nodes a netlist created, by the name the netlist gave them.
+pub struct Netlist {
+    pub nodes: HashMap<String, NodeIndex>,
+}
+
+impl Netlist {
+    pub fn node(&self, name: &str) -> Option<NodeIndex> {
+        self.nodes.get(name).map(|node| { *node })
+    }
+    
+    /// Looks up several nodes at once, such as the bits of a bus.
+    pub fn bus(&self, names: &[&str]) -> Result<Vec<NodeIndex>, String> {
+        let mut nodes = Vec::new();
+        for name in names.iter() {
+            nodes.push(try!(self.node(*name).ok_or(format!("no node named {}", name))));
+        }
+        Ok(nodes)
+    }
+}
+
+fn node_for(word: &str, nodes: &mut HashMap<String, NodeIndex>, creator: &mut NodeCreator) -> NodeIndex {
+    if let Some(node) = nodes.get(word) {
+        return *node;
+    }
+    
+    let node = creator.new_node();
+    if !word.starts_with("#") {
+        creator.name_node(node, word);
+    }
+    nodes.insert(word.to_string(), node);
+    node
+}
+
+/// Adds the netlist's elements and links to the creator.
+pub fn read_netlist(text: &str, creator: &mut NodeCreator) -> Result<Netlist, String> {
+    let mut nodes = HashMap::new();
+    let mut owners : HashMap<String, usize> = HashMap::new(); // The line of the element each node belongs to
+    let mut seen_header = false;
+    
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if !seen_header {
+            if line != HEADER {
+                return Err(format!("line {}: expected \"{}\"", line_number, HEADER));
+            }
+            seen_header = true;
+            continue;
+        }
+        
+        let words : Vec<&str> = line.split(' ').filter(|word| { !word.is_empty() }).collect();
+        match words[0] {
+            "wire" if words.len() == 3 => {
+                let a = node_for(words[1], &mut nodes, creator);
+                let b = node_for(words[2], &mut nodes, creator);
+                creator.wire(a, b);
+            },
+            "link" if words.len() == 4 => {
+                let delay = try!(words[3].parse::<u32>().map_err(|_| { format!("line {}: bad delay {}", line_number, words[3]) }));
+                let a = node_for(words[1], &mut nodes, creator);
+                let b = node_for(words[2], &mut nodes, creator);
+                creator.link(a, b, PropogationDelay(delay));
+            },
+            kind => {
+                let node_count = try!(primitive_node_count(kind).ok_or(format!("line {}: cannot read {}", line_number, line)));
+                if words.len() < 1 + node_count {
+                    return Err(format!("line {}: {} takes {} nodes", line_number, kind, node_count));
+                }
+                
+                for word in words[1..1 + node_count].iter() {
+                    if let Some(owner) = owners.get(*word) {
+                        return Err(format!("line {}: {} is already a node of the element on line {}", line_number, word, owner));
+                    }
+                    owners.insert(word.to_string(), line_number);
+                }
+                let element_nodes : Vec<NodeIndex> = words[1..1 + node_count].iter().map(|word| {
+                    node_for(*word, &mut nodes, creator)
+                }).collect();
+                let mut params = Vec::new();
+                for word in words[1 + node_count..].iter() {
+                    params.push(try!(word.parse::<u64>().map_err(|_| { format!("line {}: bad parameter {}", line_number, word) })));
+                }
+                try!(rebuild_element(kind, &element_nodes[], &params[], creator).map_err(|message| {
+                    format!("line {}: {}", line_number, message)
+                }));
+            }
+        }
+    }
+    
+    if !seen_header {
+        return Err("empty netlist".to_string());
+    }
+    Ok(Netlist {
+        nodes: nodes,
+    })
+}
+
+/// Which net each node is on, indexed by node. Nodes joined by any chain of links share
+/// a net, which is identified by its lowest node.
+pub fn nets(creator: &NodeCreator) -> Vec<NodeIndex> {
+    fn root(parents: &mut Vec<usize>, node: usize) -> usize {
+        let mut root = node;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        let mut node = node;
+        while parents[node] != root {
+            let next = parents[node];
+            parents[node] = root;
+            node = next;
+        }
+        root
+    }
+    
+    let mut parents : Vec<usize> = range(0, creator.node_count()).collect();
+    for &(a, b, _) in creator.links().iter() {
+        let a = root(&mut parents, a.0);
+        let b = root(&mut parents, b.0);
+        if a < b {
+            parents[b] = a;
+        } else {
+            parents[a] = b;
+        }
+    }
+    
+    range(0, creator.node_count()).map(|node| { NodeIndex(root(&mut parents, node)) }).collect()
+}
+
+/// Names nets for export to other formats: nets on a port after the port, and the rest
+/// n<number>, where the number is the net's lowest node, with underscores added if a port
+/// already has that name. Every port must be named before any other net.
+pub struct NetNames {
+    nets: Vec<NodeIndex>,
+    names: HashMap<NodeIndex, String>,
+    taken: HashSet<String>,
+    pub internal: Vec<String>, // The names made up for nets on no port, in the order they were made
+}
+
+impl NetNames {
+    pub fn new(creator: &NodeCreator) -> NetNames {
+        NetNames {
+            nets: nets(creator),
+            names: HashMap::new(),
+            taken: HashSet::new(),
+            internal: Vec::new(),
+        }
+    }
+    
+    /// Names the net of `node` after a port bit, such as "a" or "a[3]". If an earlier port
+    /// is on the same net, the net keeps that port's name, which is returned.
+    pub fn name_port_bit(&mut self, node: NodeIndex, name: String) -> Option<String> {
+        let net = self.nets[node.0];
+        if let Some(existing) = self.names.get(&net) {
+            return Some(existing.clone());
+        }
+        self.taken.insert(name.clone());
+        self.names.insert(net, name);
+        None
+    }
+    
+    pub fn name(&mut self, node: NodeIndex) -> String {
+        let net = self.nets[node.0];
+        if let Some(name) = self.names.get(&net) {
+            return name.clone();
+        }
+        let mut name = format!("n{}", net.0);
+        while self.taken.contains(&name) {
+            name.push('_');
+        }
+        self.taken.insert(name.clone());
+        self.internal.push(name.clone());
+        self.names.insert(net, name.clone());
+        name
+    }
+}
+
+/// Dumps everything added to the creator so far.
+pub fn write_netlist(creator: &NodeCreator) -> String {
+    let names : HashMap<NodeIndex, &str> = creator.node_names().iter().filter(|&&(_, ref name)| {
+        !name.is_empty() && !name.starts_with("#") && !name.contains(" ")
+    }).map(|&(node, ref name)| { (node, &name[]) }).collect();
+    let node_word = |node: NodeIndex| -> String {
+        match names.get(&node) {
+            Some(name) => name.to_string(),
+            None => format!("#{}", node.0),
+        }
+    };
+    
+    let mut text = format!("{}\n", HEADER);
+    for element in creator.elements().iter() {
+        text.push_str(element.kind());
+        for node in element.get_nodes().iter() {
+            text.push_str(&format!(" {}", node_word(*node))[]);
+        }
+        for param in element.params().iter() {
+            text.push_str(&format!(" {}", param)[]);
+        }
+        text.push_str("\n");
+    }
+    
+    for &(a, b, delay) in creator.links().iter() {
+        match delay {
+            Some(delay) => text.push_str(&format!("link {} {} {}\n", node_word(a), node_word(b), delay.0)[]),
+            None => text.push_str(&format!("wire {} {}\n", node_word(a), node_word(b))[]),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator};
+    use logic_gates::XorGate;
+    use truth_table::check_truth_table;
+    use super::{read_netlist, write_netlist, nets, NetNames};
+    
+    #[test]
+    fn test_read_netlist() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let netlist = read_netlist("
+                netlist 1
+                // An AND gate: a NAND followed by a NAND used as an inverter
+                Nand a b #0 0
+                Nand #1 #2 out 0
+                wire #0 #1
+                wire #0 #2
+            ", creator).unwrap();
+            
+            (netlist.bus(&["a", "b"]).unwrap(), vec![netlist.node("out").unwrap()])
+        }, &[
+            (&[0,0], &[0]),
+            (&[1,0], &[0]),
+            (&[0,1], &[0]),
+            (&[1,1], &[1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_round_trip() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        XorGate::new(&mut creator);
+        let written = write_netlist(&creator);
+        
+        let mut reread = NodeCreator::new(&c);
+        let netlist = read_netlist(&written[], &mut reread).unwrap();
+        assert!(netlist.node("XorGate0/Nand3.output").is_some());
+        assert_eq!(write_netlist(&reread), written);
+    }
+    
+    #[test]
+    fn test_nets() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let xor = XorGate::new(&mut creator);
+        let nets = nets(&creator);
+        assert_eq!(nets[xor.a.0], xor.a);
+        assert!(nets[xor.b.0] != xor.a);
+        assert_eq!(nets.iter().filter(|net| { **net == xor.a }).count(), 2); // The first NAND's input and the second's
+    }
+    
+    #[test]
+    fn test_net_names_avoid_ports() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let xor = XorGate::new(&mut creator);
+        let mut names = NetNames::new(&creator);
+        let port = format!("n{}", xor.a.0);
+        assert_eq!(names.name_port_bit(xor.output, port.clone()), None);
+        assert_eq!(names.name(xor.a), format!("{}_", port));
+        assert_eq!(names.name(xor.output), port);
+    }
+    
+    #[test]
+    fn test_errors_have_line_numbers() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        assert_eq!(read_netlist("netlist 1\nNand a b\n", &mut creator).err(), Some("line 2: Nand takes 3 nodes".to_string()));
+        assert_eq!(read_netlist("netlist 1\n\nInverter a b\n", &mut creator).err(), Some("line 3: cannot read Inverter a b".to_string()));
+        assert_eq!(read_netlist("netlist 1\nNand a b y 0\nNand c d y 0\n", &mut creator).err(), Some("line 3: y is already a node of the element on line 2".to_string()));
+        assert_eq!(read_netlist("netlist 1\nNand a a y 0\n", &mut creator).err(), Some("line 2: a is already a node of the element on line 2".to_string()));
+    }
+}
@@ -8,9 +8,12 @@ pub struct PinElem {
 
 impl PinElem {
     pub fn new(c: &mut NodeCreator) -> PinElem {
-        PinElem {
-            node: c.new_node(),
-        }
+        c.begin_component("Pin");
+        let elem = PinElem {
+            node: c.new_named_node("node"),
+        };
+        c.end_component();
+        elem
     }
 }
 
@@ -23,6 +26,10 @@ impl Element for PinElem {
         v.push(self.node);
         v
     }
+    
+    fn kind(&self) -> &'static str {
+        "Pin"
+    }
 }
 
 pub struct Pin {
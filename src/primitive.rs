@@ -0,0 +1,84 @@
+use std::u32;
+
+use sim::{NodeIndex, NodeCreator, PropogationDelay};
+use nand::NandElem;
+use pin::PinElem;
+use rom::ConstantBitElem;
+use clock::{ClockElem, ClockParams};
+
+/// How many nodes an element of `kind` has, or None if it is not a primitive kind.
+pub fn primitive_node_count(kind: &str) -> Option<usize> {
+    match kind {
+        "Nand" => Some(3),
+        "Pin" | "ConstantBit" | "Clock" => Some(1),
+        _ => None,
+    }
+}
+
+fn check_counts(kind: &str, nodes: &[NodeIndex], params: &[u64], param_counts: &[usize]) -> Result<(), String> {
+    let node_count = primitive_node_count(kind).unwrap();
+    if nodes.len() != node_count {
+        return Err(format!("{} takes {} nodes, not {}", kind, node_count, nodes.len()));
+    }
+    if !param_counts.contains(&params.len()) {
+        return Err(format!("{} cannot take {} params", kind, params.len()));
+    }
+    Ok(())
+}
+
+/// Adds an element of `kind` on existing nodes to the creator. The nodes and params are
+/// in the order the element's `get_nodes` and `params` return them.
+pub fn rebuild_element(kind: &str, nodes: &[NodeIndex], params: &[u64], creator: &mut NodeCreator) -> Result<(), String> {
+    match kind {
+        "Nand" => {
+            try!(check_counts(kind, nodes, params, &[1]));
+            if params[0] > u32::MAX as u64 {
+                return Err(format!("Nand delay {} does not fit in 32 bits", params[0]));
+            }
+            let elem = creator.arena.alloc(|| {
+                NandElem {
+                    a: nodes[0],
+                    b: nodes[1],
+                    output: nodes[2],
+                    delay: PropogationDelay(params[0] as u32),
+                }
+            });
+            creator.add_element(elem);
+        },
+        "Pin" => {
+            try!(check_counts(kind, nodes, params, &[0]));
+            let elem = creator.arena.alloc(|| { PinElem{ node: nodes[0] } });
+            creator.add_element(elem);
+        },
+        "ConstantBit" => {
+            try!(check_counts(kind, nodes, params, &[1]));
+            let elem = creator.arena.alloc(|| { ConstantBitElem{ node: nodes[0], on: params[0] != 0 } });
+            creator.add_element(elem);
+        },
+        "Clock" => {
+            try!(check_counts(kind, nodes, params, &[3, 4]));
+            if params[0] == 0 {
+                return Err("Clock period must not be 0".to_string());
+            }
+            if params[0] > u32::MAX as u64 || params[2] > u32::MAX as u64 {
+                return Err("Clock period and phase must fit in 32 bits".to_string());
+            }
+            let elem = creator.arena.alloc(|| {
+                ClockElem {
+                    node: nodes[0],
+                    params: ClockParams {
+                        period: params[0],
+                        high_ticks: params[1],
+                        phase: params[2],
+                        stop: params.get(3).map(|stop| { *stop }),
+                    },
+                }
+            });
+            creator.add_element(elem);
+        },
+        _ => {
+            return Err(format!("unknown element kind {}", kind));
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,23 @@
-use sim::{NodeIndex, NodeCreator, NodeCollection, Element, PropogationDelay, STANDARD_DELAY, LineState};
+use sim::{NodeIndex, NodeCreator, NodeCollection, Element, PropogationDelay, LineState};
 use pin::Pin;
 use mux::MuxN;
 
 use arena::Arena;
 
-struct ConstantBitElem {
-    node: NodeIndex,
-    on: bool,
+pub struct ConstantBitElem {
+    pub node: NodeIndex,
+    pub on: bool,
 }
 
 impl ConstantBitElem {
     fn new(on: bool, c: &mut NodeCreator) -> ConstantBitElem {
-        ConstantBitElem {
-            node: c.new_node(),
+        c.begin_component("ConstantBit");
+        let elem = ConstantBitElem {
+            node: c.new_named_node("node"),
             on: on,
-        }
+        };
+        c.end_component();
+        elem
     }
 }
 
@@ -29,6 +32,14 @@ impl Element for ConstantBitElem {
         v.push(self.node);
         v
     }
+    
+    fn kind(&self) -> &'static str {
+        "ConstantBit"
+    }
+    
+    fn params(&self) -> Vec<u64> {
+        vec![if self.on { 1 } else { 0 }]
+    }
 }
 
 pub struct ConstantBit {
@@ -59,10 +70,14 @@ impl ConstantBits {
     }
 
     pub fn new(bits: &[bool], creator: &mut NodeCreator) -> ConstantBits {
+        creator.begin_component("ConstantBits");
+        let nodes = bits.iter().map(|bit_on| {
+            ConstantBit::new(*bit_on, creator).node
+        }).collect();
+        creator.end_component();
+        
         ConstantBits {
-            bits: bits.iter().map(|bit_on| {
-                ConstantBit::new(*bit_on, creator).node
-            }).collect()
+            bits: nodes
         }
     }
 }
@@ -75,6 +90,7 @@ pub struct Rom {
 }
 impl Rom {
     pub fn new(content: &[&[bool]], creator: &mut NodeCreator) -> Rom {
+        creator.begin_component("Rom");
         let word_count = content.len();
         assert!(word_count>0);
         let word_bits = content[0].len();
@@ -83,9 +99,10 @@ impl Rom {
         assert_eq!(content.len(), mux.inputs.len());
         for (content_word, mux_input_word) in content.iter().zip(mux.inputs.iter()) {
             let constant_generator = ConstantBits::new(*content_word, creator);
-            creator.multilink(&constant_generator.bits[], &mux_input_word[], STANDARD_DELAY);
+            creator.multiwire(&constant_generator.bits[], &mux_input_word[]);
         }
         
+        creator.end_component();
         Rom {
             address: mux.select,
             output: mux.output,
@@ -0,0 +1,127 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use sim::{NodeCreator, NodeIndex, NodeCollection, LineState, PropogationDelay};
+use pin::Pin;
+
+pub struct SettleReport {
+    /// For each transition, how many ticks each output took to reach its final value.
+    pub settle_times: Vec<Vec<u64>>,
+    pub worst_case: u64,
+}
+
+fn write_inputs(input_pins: &[Pin], input_values: &[u8], c: &mut NodeCollection) {
+    assert!(input_values.len() == input_pins.len());
+    for (input_pin, input_value) in input_pins.iter().zip(input_values.iter()) {
+        assert!(*input_value==0 || *input_value==1);
+        input_pin.node.write(if *input_value==1 { LineState::High } else { LineState::Low }, c);
+    }
+}
+
+/// Settles the circuit on the first inputs of each transition, switches to the second
+/// inputs and measures how long each output keeps changing afterwards.
+/// The input pins are linked with no delay, so only the circuit itself is measured.
+pub fn measure_settle_times<F>(f: F, transitions: &[(&[u8], &[u8])]) -> SettleReport
+    where F: FnOnce(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
+{
+    let mut c = NodeCollection::new();
+    let mut creator = NodeCreator::new(&c);
+    
+    let (inputs, outputs) = f(&mut creator);
+    let input_pins : Vec<Pin> = inputs.iter().map(|input| {
+        let p = Pin::new(&mut creator);
+        creator.link(*input, p.node, PropogationDelay(0));
+        p
+    }).collect();
+    
+    c.absorb(creator);
+    
+    let last_changes = Rc::new(RefCell::new(range(0, outputs.len()).map(|_| { 0u64 }).collect::<Vec<u64>>()));
+    for (output_index, output) in outputs.iter().enumerate() {
+        let recorder = last_changes.clone();
+        c.watch(*output, move |tick, _, _| {
+            recorder.borrow_mut()[output_index] = tick;
+        });
+    }
+    
+    let mut settle_times = Vec::new();
+    let mut worst_case = 0;
+    for &(before, after) in transitions.iter() {
+        write_inputs(&input_pins[], before, &mut c);
+        while c.play() {}
+        
+        let start = c.current_tick;
+        for last_change in last_changes.borrow_mut().iter_mut() {
+            *last_change = start;
+        }
+        
+        write_inputs(&input_pins[], after, &mut c);
+        while c.play() {}
+        
+        let times : Vec<u64> = last_changes.borrow().iter().map(|tick| { *tick - start }).collect();
+        for time in times.iter() {
+            if *time > worst_case {
+                worst_case = *time;
+            }
+        }
+        settle_times.push(times);
+    }
+    
+    SettleReport {
+        settle_times: settle_times,
+        worst_case: worst_case,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sim::NodeCreator;
+    use logic_gates::NWayAnd;
+    use adder::RippleCarryAdder;
+    use super::measure_settle_times;
+    
+    #[test]
+    fn test_logtime_and_settles_faster() {
+        let transitions : &[(&[u8], &[u8])] = &[
+            (&[0,1,1,1,1,1,1,1], &[1,1,1,1,1,1,1,1]),
+            (&[1,1,1,1,1,1,1,1], &[0,1,1,1,1,1,1,1]),
+        ];
+        
+        let chain = measure_settle_times(|creator: &mut NodeCreator| {
+            let and = NWayAnd::new(creator, 8);
+            (and.inputs.clone(), [and.output].to_vec())
+        }, transitions);
+        let tree = measure_settle_times(|creator: &mut NodeCreator| {
+            let and = NWayAnd::new_logtime(creator, 8);
+            (and.inputs.clone(), [and.output].to_vec())
+        }, transitions);
+        
+        assert!(tree.worst_case > 0);
+        assert!(tree.worst_case < chain.worst_case);
+    }
+    
+    #[test]
+    fn test_carry_ripples() {
+        let report = measure_settle_times(|creator: &mut NodeCreator| {
+            let adder = RippleCarryAdder::new(creator, 8);
+            
+            let mut inputs = adder.a.clone();
+            inputs.append(&mut adder.b.clone());
+            inputs.push(adder.carry_in);
+            
+            let mut outputs = adder.sum.clone();
+            outputs.push(adder.carry_out);
+            
+            (inputs, outputs)
+        }, &[
+            // 0xFF + 0 -> 0xFF + 1
+            (&[1,1,1,1,1,1,1,1, 0,0,0,0,0,0,0,0, 0], &[1,1,1,1,1,1,1,1, 1,0,0,0,0,0,0,0, 0]),
+        ]);
+        
+        let times = &report.settle_times[0];
+        for bit in range(1, 8) {
+            assert!(times[bit] > times[bit-1]);
+        }
+        assert!(report.worst_case >= times[7]);
+    }
+}
@@ -1,10 +1,14 @@
 
 use std::collections::binary_heap::BinaryHeap;
+use std::collections::HashMap;
 use std::cmp::PartialOrd;
 use std::cmp::{Ord, Ordering};
 
 use arena::Arena;
-use std::mem::transmute;
+use std::mem::{transmute, replace};
+use std::u32;
+
+use jitter::{Jitter, DelayScaler};
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum LineState {
@@ -26,7 +30,7 @@ impl LineState {
 }
 
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct NodeIndex(pub usize);
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -35,17 +39,58 @@ pub struct ElementIndex(pub usize);
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct PropogationDelay(pub u32);
 
+/// The delays builders use, so that one circuit can be built under different technology assumptions.
+#[derive(Clone, Copy, Debug)]
+pub struct DelayLibrary {
+    pub nand: PropogationDelay, // From a NAND's inputs changing to its output changing
+    pub wire: PropogationDelay,
+    pub wire_per_fanout: PropogationDelay, // Added to a wire for every other wire sharing its busiest end
+}
+
+impl DelayLibrary {
+    pub fn standard() -> DelayLibrary {
+        DelayLibrary {
+            nand: PropogationDelay(0),
+            wire: STANDARD_DELAY,
+            wire_per_fanout: PropogationDelay(0),
+        }
+    }
+    
+    fn wire_delay(&self, fanout: u32) -> PropogationDelay {
+        let extra_wires = if fanout > 0 { fanout - 1 } else { 0 };
+        PropogationDelay(self.wire.get().saturating_add(self.wire_per_fanout.get().saturating_mul(extra_wires)))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LinkDelay {
+    Fixed(PropogationDelay),
+    Wire, // Resolved against the creator's DelayLibrary once all the wires are known
+    Exact(PropogationDelay), // Kept as it is, without jitter
+}
+
 impl PropogationDelay {
     fn get(self) -> u32 {
         let PropogationDelay(ticks) = self;
         ticks
     }
+    
+    fn scaled(self, percent: u32) -> PropogationDelay {
+        let ticks = (self.get() as u64) * (percent as u64) / 100;
+        PropogationDelay(if ticks > u32::MAX as u64 { u32::MAX } else { ticks as u32 })
+    }
 }
 
 
 pub trait Element {
     fn step(&self, c: &mut NodeCollection);
     fn get_nodes(&self) -> Vec<NodeIndex>;
+    fn kind(&self) -> &'static str;
+    
+    /// Whatever besides its nodes is needed to rebuild the element, see `primitive::rebuild_element`.
+    fn params(&self) -> Vec<u64> {
+        Vec::new()
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -66,7 +111,14 @@ struct Node {
     output_state: LineState,
     linked_with: Vec<Link>,
     element_index: Option<ElementIndex>,
-    influences: Vec<Influence>
+    influences: Vec<Influence>,
+    watchers: Vec<usize>,
+    events_played: u64,
+    toggles: u64, // Changes of the resolved state between Low and High
+    seen_low: bool,
+    seen_high: bool,
+    name: Option<String>,
+    forced: Option<LineState>, // Overrides both what the node reads and what it outputs
 }
 
 impl Node {
@@ -76,10 +128,21 @@ impl Node {
             linked_with: Vec::new(),
             element_index: None,
             influences: Vec::new(),
+            watchers: Vec::new(),
+            events_played: 0,
+            toggles: 0,
+            seen_low: false,
+            seen_high: false,
+            name: None,
+            forced: None,
         }
     }
     
     fn get_input_state(&self) -> LineState {
+        if let Some(forced) = self.forced {
+            return forced;
+        }
+        
         let mut lows = 0;
         let mut highs = 0;
         
@@ -127,6 +190,85 @@ impl Ord for LineStateEvent {
     }
 }
 
+/// An event waiting to be played, as seen from outside the collection.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PendingEvent {
+    pub node: NodeIndex,
+    pub new_state: LineState,
+    pub time: u64,
+    pub forcer: NodeIndex,
+}
+
+/// What a played event overwrote, so that it can be put back in the queue.
+struct PlayedEvent {
+    event: LineStateEvent,
+    old_influence: Option<Influence>, // None if the forcer had no influence on the node yet
+    old_events_played: u64,
+    old_toggles: u64,
+    old_seen: (bool, bool),
+}
+
+/// Everything one call to `play`, `force`, `release` or an outside write changed.
+struct HistoryStep {
+    tick: u64,
+    event_id_counter: u64, // Any event with a higher id was scheduled during this step
+    force_id_counter: u64,
+    played: Option<PlayedEvent>,
+    output_states: Vec<(NodeIndex, LineState)>, // What each write overwrote, oldest first
+    forced: Option<(NodeIndex, Option<LineState>, Vec<Influence>)>, // The node, what it was forced to and its influences
+}
+
+impl HistoryStep {
+    /// The tick at which this step's change appeared.
+    fn time(&self) -> u64 {
+        match self.played {
+            Some(ref played) => played.event.time,
+            None => self.tick,
+        }
+    }
+}
+
+/// A collection's simulation state, without its elements and links, as plain data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedState {
+    pub current_tick: u64,
+    pub event_id_counter: u64,
+    pub force_id_counter: u64,
+    pub events_played: u64,
+    pub peak_pending_events: usize,
+    pub nodes: Vec<SavedNode>,
+    pub events: Vec<SavedEvent>,
+    pub element_delay_percents: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedNode {
+    pub name: Option<String>,
+    pub output_state: LineState,
+    pub forced: Option<LineState>,
+    pub influences: Vec<(NodeIndex, LineState, u64)>, // The forcer, its state and its force id
+    pub events_played: u64,
+    pub toggles: u64,
+    pub seen_low: bool,
+    pub seen_high: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SavedEvent {
+    pub node: NodeIndex,
+    pub new_state: LineState,
+    pub time: u64,
+    pub id: u64,
+    pub forcer: NodeIndex,
+    pub force_id: u64,
+}
+
+struct Watcher<'a> {
+    nodes: Vec<NodeIndex>,
+    last_states: Vec<LineState>,
+    callback: Box<FnMut(u64, &[LineState], &[LineState]) + 'a>,
+}
+
 pub struct NodeCollection<'a> {
     nodes: Vec<Node>,
     events: BinaryHeap<LineStateEvent>,
@@ -136,6 +278,15 @@ pub struct NodeCollection<'a> {
     event_id_counter: u64,
     link_id_counter: u64,
     force_id_counter: u64,
+    watchers: Vec<Watcher<'a>>,
+    event_listeners: Vec<Box<FnMut(&PendingEvent, LineState) + 'a>>,
+    delay_scaler: Option<DelayScaler>,
+    element_delay_percents: Vec<u32>,
+    events_played: u64,
+    peak_pending_events: usize,
+    history: Option<Vec<HistoryStep>>, // None unless history is being recorded
+    history_limit: Option<usize>, // How many steps to keep, if not all of them
+    recording_step: bool,
 }
 
 impl<'a> NodeCollection<'a> {
@@ -150,6 +301,27 @@ impl<'a> NodeCollection<'a> {
             event_id_counter: 0,
             link_id_counter: 0,
             force_id_counter: 0,
+            watchers: Vec::new(),
+            event_listeners: Vec::new(),
+            delay_scaler: None,
+            element_delay_percents: Vec::new(),
+            events_played: 0,
+            peak_pending_events: 0,
+            history: None,
+            history_limit: None,
+            recording_step: false,
+        }
+    }
+    
+    /// Randomly perturbs the delay of every link and element absorbed from now on.
+    pub fn set_jitter(&mut self, jitter: Jitter) {
+        self.delay_scaler = Some(jitter.scaler());
+    }
+    
+    fn next_delay_percent(&mut self) -> u32 {
+        match self.delay_scaler {
+            Some(ref mut scaler) => scaler.next_percent(),
+            None => 100,
         }
     }
     
@@ -186,7 +358,54 @@ impl<'a> NodeCollection<'a> {
     
     fn play_event(&mut self, e: LineStateEvent) {
         self.current_tick = e.time;
+        
+        if self.history_step().is_some() {
+            let played = {
+                let target = &self.nodes[e.node.get()];
+                PlayedEvent {
+                    event: e,
+                    old_influence: target.influences.iter().find(|&: influence| { influence.force_generator == e.forcer }).map(|influence| { *influence }),
+                    old_events_played: target.events_played,
+                    old_toggles: target.toggles,
+                    old_seen: (target.seen_low, target.seen_high),
+                }
+            };
+            self.history_step().unwrap().played = Some(played);
+        }
+        
+        let old_state = e.node.read(self);
         self.apply_influence(&e);
+        let new_state = e.node.read(self);
+        
+        self.events_played += 1;
+        {
+            let target = &mut self.nodes[e.node.get()];
+            target.events_played += 1;
+            match (old_state, new_state) {
+                (LineState::Low, LineState::High) | (LineState::High, LineState::Low) => { target.toggles += 1; }
+                _ => {}
+            }
+            match new_state {
+                LineState::Low => { target.seen_low = true; }
+                LineState::High => { target.seen_high = true; }
+                _ => {}
+            }
+        }
+        
+        if new_state != old_state {
+            self.notify_watchers(e.node);
+        }
+        if !self.event_listeners.is_empty() {
+            let played = PendingEvent{ node: e.node, new_state: e.new_state, time: e.time, forcer: e.forcer };
+            for listener in self.event_listeners.iter_mut() {
+                (**listener)(&played, new_state);
+            }
+        }
+        
+        // A forced node holds its net at the forced state, passing on only its own influence.
+        if self.nodes[e.node.get()].forced.is_some() && e.forcer != e.node {
+            return;
+        }
         
         let target = &self.nodes[e.node.get()];
         
@@ -215,7 +434,78 @@ impl<'a> NodeCollection<'a> {
         }
     }
     
+    // Which nodes `forcer` reaches without passing through `through`.
+    fn reachable_around(&self, forcer: NodeIndex, through: NodeIndex) -> Vec<bool> {
+        let mut reached : Vec<bool> = range(0, self.nodes.len()).map(|_| { false }).collect();
+        reached[forcer.get()] = true;
+        let mut frontier = vec![forcer];
+        while let Some(node) = frontier.pop() {
+            for link in self.nodes[node.get()].linked_with.iter() {
+                let next = link.linked_to;
+                if next != through && !reached[next.get()] {
+                    reached[next.get()] = true;
+                    frontier.push(next);
+                }
+            }
+        }
+        reached
+    }
+    
+    fn notify_watchers(&mut self, node: NodeIndex) {
+        let watcher_indices = self.nodes[node.get()].watchers.clone();
+        let tick = self.current_tick;
+        for watcher_index in watcher_indices.into_iter() {
+            let states : Vec<LineState> = self.watchers[watcher_index].nodes.iter().map(|node| { node.read(self) }).collect();
+            
+            let watcher = &mut self.watchers[watcher_index];
+            if states != watcher.last_states {
+                (*watcher.callback)(tick, &watcher.last_states[], &states[]);
+                watcher.last_states = states;
+            }
+        }
+    }
+    
+    /// Calls `callback` with the tick, the old state and the new state whenever the
+    /// resolved state of `node` changes. The node must already have been absorbed.
+    pub fn watch<F>(&mut self, node: NodeIndex, mut callback: F)
+        where F: FnMut(u64, LineState, LineState) + 'a
+    {
+        self.watch_bus(&[node], move |tick, old, new| {
+            callback(tick, old[0], new[0])
+        });
+    }
+    
+    /// Like `watch`, but fires when any node of a bus changes, passing the whole bus.
+    pub fn watch_bus<F>(&mut self, nodes: &[NodeIndex], callback: F)
+        where F: FnMut(u64, &[LineState], &[LineState]) + 'a
+    {
+        let watcher_index = self.watchers.len();
+        for node in nodes.iter() {
+            self.nodes[node.get()].watchers.push(watcher_index);
+        }
+        
+        let last_states = nodes.iter().map(|node| { node.read(self) }).collect();
+        self.watchers.push(Watcher{
+            nodes: nodes.to_vec(),
+            last_states: last_states,
+            callback: Box::new(callback),
+        });
+    }
+    
+    /// Calls `callback` after every event is played, with the event and the resolved state
+    /// of its node afterwards, whether or not that changed.
+    pub fn on_event<F>(&mut self, callback: F)
+        where F: FnMut(&PendingEvent, LineState) + 'a
+    {
+        self.event_listeners.push(Box::new(callback));
+    }
+    
     pub fn play(&mut self) -> bool {
+        if self.events.is_empty() {
+            return false;
+        }
+        
+        let started = self.begin_history_step();
         if let Some(evt) = self.events.pop() {
             //println!("Playing event: {:?}", evt);
             
@@ -225,19 +515,485 @@ impl<'a> NodeCollection<'a> {
             if let Some(element_index) = maybe_element_index{
                 self.elements[element_index.get()].step(self);
             }
+            self.note_pending_events();
+            self.end_history_step(started);
             return true;
         } else {
             return false;
         }
     }
     
+    /// Holds a node at `state` regardless of what drives it, until it is released.
+    /// If the node is itself a driver, everything it drives sees `state` too.
+    pub fn force(&mut self, node: NodeIndex, state: LineState) {
+        self.set_forced(node, Some(state));
+    }
+    
+    pub fn release(&mut self, node: NodeIndex) {
+        self.set_forced(node, None);
+    }
+    
+    fn set_forced(&mut self, node: NodeIndex, forced: Option<LineState>) {
+        let started = self.begin_history_step();
+        let old_forced = self.nodes[node.get()].forced;
+        let old_influences = self.nodes[node.get()].influences.clone();
+        if let Some(step) = self.history_step() {
+            step.forced = Some((node, old_forced, old_influences));
+        }
+        
+        let old_state = node.read(self);
+        self.nodes[node.get()].forced = forced;
+        self.after_override(node, old_state);
+        self.end_history_step(started);
+    }
+    
+    pub fn forced_state(&self, node: NodeIndex) -> Option<LineState> {
+        self.nodes[node.get()].forced
+    }
+    
+    fn after_override(&mut self, node: NodeIndex, old_state: LineState) {
+        // The node drives its net with the forced state, even if nothing ever wrote to it.
+        // On release it goes back to what it last output, which for a node nothing wrote
+        // is Floating, taking its influence back off the net.
+        let (state, forced, others) = {
+            let target = &self.nodes[node.get()];
+            let others : Vec<Influence> = target.influences.iter().filter(|&: influence| { influence.force_generator != node }).map(|influence| { *influence }).collect();
+            (target.forced.unwrap_or(target.output_state), target.forced.is_some(), others)
+        };
+        self.emit(node, state, PropogationDelay(0));
+        
+        // Since a forced node passes on no other driver, their influence is taken back off
+        // the nodes it only reached through this one, and put back on release. The node takes
+        // the new force id itself, so that the change is not echoed back onto it.
+        for influence in others.iter() {
+            let forcer = influence.force_generator;
+            let reached = self.reachable_around(forcer, node);
+            self.force_id_counter += 1;
+            let force_id = self.force_id_counter;
+            for own in self.nodes[node.get()].influences.iter_mut() {
+                if own.force_generator == forcer {
+                    own.force_id = force_id;
+                }
+            }
+            
+            let links = self.nodes[node.get()].linked_with.clone();
+            for link in links.iter().filter(|&: link| { !reached[link.linked_to.get()] }) {
+                self.event_id_counter += 1;
+                let evt = LineStateEvent{
+                    node: link.linked_to,
+                    new_state: if forced { LineState::Floating } else { influence.force_kind },
+                    time: self.current_tick + link.delay.get() as u64,
+                    id: self.event_id_counter,
+                    forcer: forcer,
+                    force_id: force_id,
+                };
+                self.events.push(evt);
+            }
+        }
+        self.note_pending_events();
+        
+        if node.read(self) != old_state {
+            self.notify_watchers(node);
+        }
+        
+        if let Some(element_index) = self.nodes[node.get()].element_index {
+            self.elements[element_index.get()].step(self);
+        }
+    }
+    
+    /// Starts or stops recording every change, so that `step_back` can undo them.
+    /// Stopping, like absorbing new elements, forgets what was recorded.
+    pub fn record_history(&mut self, enabled: bool) {
+        self.history = if enabled { Some(Vec::new()) } else { None };
+    }
+    
+    /// Keeps only the most recent `steps` steps of history, forgetting older ones as new ones
+    /// are recorded, or every step if None.
+    pub fn limit_history(&mut self, steps: Option<usize>) {
+        self.history_limit = steps;
+        if let Some(limit) = steps {
+            if let Some(ref mut history) = self.history {
+                while history.len() > limit {
+                    history.remove(0);
+                }
+            }
+        }
+    }
+    
+    /// Forgets every recorded step, carrying on recording if it was.
+    pub fn clear_history(&mut self) {
+        if let Some(ref mut steps) = self.history {
+            steps.clear();
+        }
+    }
+    
+    /// How many steps `step_back` can undo.
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map(|steps| { steps.len() }).unwrap_or(0)
+    }
+    
+    /// The tick of the most recent change that `step_back` would undo.
+    pub fn last_recorded_time(&self) -> Option<u64> {
+        match self.history {
+            Some(ref steps) => steps.last().map(|step| { step.time() }),
+            None => None,
+        }
+    }
+    
+    // Returns whether this call started a step, rather than joining the one in progress.
+    fn begin_history_step(&mut self) -> bool {
+        if self.recording_step {
+            return false;
+        }
+        
+        let step = HistoryStep {
+            tick: self.current_tick,
+            event_id_counter: self.event_id_counter,
+            force_id_counter: self.force_id_counter,
+            played: None,
+            output_states: Vec::new(),
+            forced: None,
+        };
+        match self.history {
+            Some(ref mut steps) => {
+                if self.history_limit.map(|limit| { steps.len() >= limit }).unwrap_or(false) {
+                    steps.remove(0);
+                }
+                steps.push(step);
+            },
+            None => { return false; },
+        }
+        self.recording_step = true;
+        true
+    }
+    
+    fn end_history_step(&mut self, started: bool) {
+        if started {
+            self.recording_step = false;
+        }
+    }
+    
+    fn history_step(&mut self) -> Option<&mut HistoryStep> {
+        if !self.recording_step {
+            return None;
+        }
+        match self.history {
+            Some(ref mut steps) => steps.last_mut(),
+            None => None,
+        }
+    }
+    
+    /// Undoes the most recent recorded step: the event it played goes back in the queue,
+    /// and the events it scheduled are dropped. Returns false if nothing is recorded.
+    /// Watchers and event listeners are not told about undone changes: they carry on from
+    /// the last change they saw played.
+    pub fn step_back(&mut self) -> bool {
+        let step = match self.history {
+            Some(ref mut steps) => steps.pop(),
+            None => None,
+        };
+        let step = match step {
+            Some(step) => step,
+            None => { return false; }
+        };
+        
+        self.current_tick = step.tick;
+        self.event_id_counter = step.event_id_counter;
+        self.force_id_counter = step.force_id_counter;
+        
+        let events = replace(&mut self.events, BinaryHeap::new()).into_vec();
+        self.events = events.into_iter().filter(|evt| { evt.id <= step.event_id_counter }).collect();
+        
+        for &(node, state) in step.output_states.iter().rev() {
+            self.nodes[node.get()].output_state = state;
+        }
+        
+        if let Some((node, forced, influences)) = step.forced {
+            self.nodes[node.get()].forced = forced;
+            self.nodes[node.get()].influences = influences;
+        }
+        
+        if let Some(played) = step.played {
+            let node = played.event.node;
+            {
+                let target = &mut self.nodes[node.get()];
+                target.influences.retain(|influence| { influence.force_generator != played.event.forcer });
+                if let Some(influence) = played.old_influence {
+                    target.influences.push(influence);
+                }
+                target.events_played = played.old_events_played;
+                target.toggles = played.old_toggles;
+                target.seen_low = played.old_seen.0;
+                target.seen_high = played.old_seen.1;
+            }
+            self.events_played -= 1;
+            self.events.push(played.event);
+        }
+        true
+    }
+    
+    /// Undoes every recorded change that appeared after `tick`, leaving the circuit as it was then.
+    pub fn step_back_to(&mut self, tick: u64) {
+        loop {
+            match self.last_recorded_time() {
+                Some(time) if time > tick => { self.step_back(); },
+                _ => { break; }
+            }
+        }
+        
+        if self.current_tick < tick {
+            self.current_tick = tick;
+        }
+    }
+    
+    // Schedules `node` to push `state` onto itself and everything linked to it.
+    fn emit(&mut self, node: NodeIndex, state: LineState, delay: PropogationDelay) {
+        self.event_id_counter += 1;
+        self.force_id_counter += 1;
+        let evt = LineStateEvent{
+            node: node,
+            new_state: state,
+            time: self.current_tick + delay.get() as u64,
+            id: self.event_id_counter,
+            forcer: node,
+            force_id: self.force_id_counter,
+        };
+        self.events.push(evt);
+        self.note_pending_events();
+    }
+    
+    fn note_pending_events(&mut self) {
+        if self.events.len() > self.peak_pending_events {
+            self.peak_pending_events = self.events.len();
+        }
+    }
+    
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    
+    pub fn events_played(&self) -> u64 {
+        self.events_played
+    }
+    
+    /// The most events that have been waiting to be played at once, since the collection was
+    /// made or the peak was last reset.
+    pub fn peak_pending_events(&self) -> usize {
+        self.peak_pending_events
+    }
+    
+    /// Starts measuring the peak again from the events waiting now.
+    pub fn reset_peak_pending_events(&mut self) {
+        self.peak_pending_events = self.events.len();
+    }
+    
+    pub fn node_events_played(&self, node: NodeIndex) -> u64 {
+        self.nodes[node.get()].events_played
+    }
+    
+    pub fn node_toggles(&self, node: NodeIndex) -> u64 {
+        self.nodes[node.get()].toggles
+    }
+    
+    /// Whether the node has been resolved to Low and to High by any played event.
+    pub fn node_seen_states(&self, node: NodeIndex) -> (bool, bool) {
+        let node = &self.nodes[node.get()];
+        (node.seen_low, node.seen_high)
+    }
+    
+    /// The hierarchical name the node was created with, or its index if it has none.
+    pub fn node_name(&self, node: NodeIndex) -> String {
+        match self.nodes[node.get()].name {
+            Some(ref name) => name.clone(),
+            None => format!("#{}", node.get()),
+        }
+    }
+    
+    pub fn find_node(&self, name: &str) -> Option<NodeIndex> {
+        range(0, self.nodes.len()).map(|index| { NodeIndex(index) }).find(|node| {
+            self.node_name(*node) == name
+        })
+    }
+    
+    pub fn elements(&self) -> &[&'a (Element + 'a)] {
+        &self.elements[]
+    }
+    
+    /// Every link once, with the delay it ended up with after jitter. They come in the order
+    /// they were made, so linking them again in this order propagates events in the same order.
+    pub fn links(&self) -> Vec<(NodeIndex, NodeIndex, PropogationDelay)> {
+        let mut links = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            for link in node.linked_with.iter() {
+                if index < link.linked_to.get() {
+                    links.push((link.id, NodeIndex(index), link.linked_to, link.delay));
+                }
+            }
+        }
+        links.sort_by(|a, b| { a.0.cmp(&b.0) });
+        links.iter().map(|&(_, a, b, delay)| { (a, b, delay) }).collect()
+    }
+    
+    pub fn save_state(&self) -> SavedState {
+        SavedState {
+            current_tick: self.current_tick,
+            event_id_counter: self.event_id_counter,
+            force_id_counter: self.force_id_counter,
+            events_played: self.events_played,
+            peak_pending_events: self.peak_pending_events,
+            nodes: self.nodes.iter().map(|node| {
+                SavedNode {
+                    name: node.name.clone(),
+                    output_state: node.output_state,
+                    forced: node.forced,
+                    influences: node.influences.iter().map(|influence| {
+                        (influence.force_generator, influence.force_kind, influence.force_id)
+                    }).collect(),
+                    events_played: node.events_played,
+                    toggles: node.toggles,
+                    seen_low: node.seen_low,
+                    seen_high: node.seen_high,
+                }
+            }).collect(),
+            events: self.events.iter().map(|evt| {
+                SavedEvent {
+                    node: evt.node,
+                    new_state: evt.new_state,
+                    time: evt.time,
+                    id: evt.id,
+                    forcer: evt.forcer,
+                    force_id: evt.force_id,
+                }
+            }).collect(),
+            element_delay_percents: self.element_delay_percents.clone(),
+        }
+    }
+    
+    /// Overwrites the simulation state of a collection that has the same elements and links
+    /// as the one `state` was saved from. Whatever absorbing them scheduled is thrown away.
+    pub fn restore_state(&mut self, state: SavedState) {
+        assert_eq!(state.nodes.len(), self.nodes.len());
+        assert_eq!(state.element_delay_percents.len(), self.elements.len());
+        
+        self.current_tick = state.current_tick;
+        self.event_id_counter = state.event_id_counter;
+        self.force_id_counter = state.force_id_counter;
+        self.events_played = state.events_played;
+        self.peak_pending_events = state.peak_pending_events;
+        self.element_delay_percents = state.element_delay_percents;
+        
+        for (node, saved) in self.nodes.iter_mut().zip(state.nodes.into_iter()) {
+            node.name = saved.name;
+            node.output_state = saved.output_state;
+            node.forced = saved.forced;
+            node.influences = saved.influences.iter().map(|&(forcer, state, force_id)| {
+                Influence {
+                    force_generator: forcer,
+                    force_kind: state,
+                    force_id: force_id,
+                }
+            }).collect();
+            node.events_played = saved.events_played;
+            node.toggles = saved.toggles;
+            node.seen_low = saved.seen_low;
+            node.seen_high = saved.seen_high;
+        }
+        
+        self.events = state.events.iter().map(|evt| {
+            LineStateEvent {
+                node: evt.node,
+                new_state: evt.new_state,
+                time: evt.time,
+                id: evt.id,
+                forcer: evt.forcer,
+                force_id: evt.force_id,
+            }
+        }).collect();
+        
+        if let Some(ref mut steps) = self.history {
+            steps.clear();
+        }
+    }
+    
+    pub fn element_kinds(&self) -> Vec<&'static str> {
+        self.elements.iter().map(|element| { element.kind() }).collect()
+    }
+    
+    /// Which nodes are influencing this node, and what each is pushing it towards.
+    pub fn influences(&self, node: NodeIndex) -> Vec<(NodeIndex, LineState)> {
+        self.nodes[node.get()].influences.iter().map(|influence| {
+            (influence.force_generator, influence.force_kind)
+        }).collect()
+    }
+    
+    /// Every event not yet played, in the order they will be played.
+    pub fn pending_events(&self) -> Vec<PendingEvent> {
+        let mut events : Vec<LineStateEvent> = self.events.iter().map(|evt| { *evt }).collect();
+        events.sort_by(|a, b| { b.cmp(a) }); // The ordering is reversed to make BinaryHeap a min-heap
+        events.iter().map(|evt| {
+            PendingEvent {
+                node: evt.node,
+                new_state: evt.new_state,
+                time: evt.time,
+                forcer: evt.forcer,
+            }
+        }).collect()
+    }
+    
+    pub fn next_event_time(&self) -> Option<u64> {
+        self.events.peek().map(|evt| { evt.time })
+    }
+    
+    /// Plays every event scheduled at or before `tick`, then advances the clock to `tick`.
+    pub fn play_until(&mut self, tick: u64) {
+        loop {
+            let due = match self.events.peek() {
+                Some(evt) => evt.time <= tick,
+                None => false,
+            };
+            if !due { break; }
+            self.play();
+        }
+        
+        if self.current_tick < tick {
+            self.current_tick = tick;
+        }
+    }
+    
     pub fn absorb<'b:'a>(&mut self, creator: NodeCreator<'b>) {
+        // Undoing past this point would leave the new elements half set up.
+        if let Some(ref mut steps) = self.history {
+            steps.clear();
+        }
+        
+        // Some nodes belong to no element, and only exist to be linked to.
+        while self.nodes.len() < creator.creation_index {
+            self.nodes.push(Node::new());
+        }
+        
         for element in creator.elements.iter() {
             self.add_element(*element);
         }
         
+        for &(node, ref name) in creator.node_names.iter() {
+            self.nodes[node.get()].name = Some(name.clone());
+        }
+        
+        let fanouts = creator.wire_fanouts();
         for &(a, b, delay) in creator.links.iter() {
-            self.link(a, b, delay);
+            let delay = match delay {
+                LinkDelay::Fixed(delay) => delay,
+                LinkDelay::Wire => {
+                    let fanout = if fanouts[a.get()] > fanouts[b.get()] { fanouts[a.get()] } else { fanouts[b.get()] };
+                    creator.delays.wire_delay(fanout)
+                },
+                LinkDelay::Exact(delay) => {
+                    self.link(a, b, delay);
+                    continue;
+                },
+            };
+            let percent = self.next_delay_percent();
+            self.link(a, b, delay.scaled(percent));
         }
         
         for i in range(0, self.elements.len()) {
@@ -260,7 +1016,8 @@ impl<'a> NodeCollection<'a> {
         }
     
         self.elements.push(elem);
-        
+        let percent = self.next_delay_percent();
+        self.element_delay_percents.push(percent);
     }
 }
 
@@ -278,54 +1035,148 @@ impl NodeIndex {
     }
 
     pub fn write(self, new_state: LineState, c: &mut NodeCollection) {
-        if new_state == c.nodes[self.get()].output_state {
-            return; // no-op
-        }
-        self.write_later(new_state, PropogationDelay(0), c)
+        self.write_after(new_state, PropogationDelay(0), c)
+    }
+    
+    /// Like `write`, but the new state only appears after `delay`. This is how elements
+    /// model their own delay, so the delay is subject to the collection's jitter.
+    pub fn write_after(self, new_state: LineState, delay: PropogationDelay, c: &mut NodeCollection) {
+        let delay = {
+            let node = &c.nodes[self.get()];
+            if new_state == node.output_state {
+                return; // no-op
+            }
+            
+            match node.element_index {
+                Some(element_index) => delay.scaled(c.element_delay_percents[element_index.get()]),
+                None => delay,
+            }
+        };
+        self.write_later(new_state, delay, c)
     }
     
     pub fn write_later(self, new_state: LineState, delta_time: PropogationDelay, c: &mut NodeCollection) {
-        let node = &mut c.nodes[self.get()];
-        
-        node.output_state = new_state;
-        c.event_id_counter += 1;
-        c.force_id_counter += 1;
+        let started = c.begin_history_step();
+        let old_output_state = c.nodes[self.get()].output_state;
+        if let Some(step) = c.history_step() {
+            step.output_states.push((self, old_output_state));
+        }
         
-        let evt = LineStateEvent{
-            node: self,
-            new_state: new_state,
-            time: c.current_tick + delta_time.get() as u64,
-            id: c.event_id_counter,
-            forcer: self,
-            force_id: c.force_id_counter,
+        let emitted_state = {
+            let node = &mut c.nodes[self.get()];
+            node.output_state = new_state;
+            node.forced.unwrap_or(new_state)
         };
-        c.events.push(evt);
+        c.emit(self, emitted_state, delta_time);
+        c.end_history_step(started);
     }
     
     pub fn read(self, c: &NodeCollection) -> LineState {
         c.nodes[self.get()].get_input_state()
     }
+    
+    /// The state this node was most recently told to output, even if that write has not been played yet.
+    pub fn last_written(self, c: &NodeCollection) -> LineState {
+        c.nodes[self.get()].output_state
+    }
 }
 
 
+struct Scope {
+    path: String,
+    child_counts: HashMap<String, usize>,
+}
+
 pub struct NodeCreator<'a> {
     creation_index: usize,
     elements: Vec<&'a (Element + 'a)>,
-    links: Vec<(NodeIndex, NodeIndex, PropogationDelay)>,
+    links: Vec<(NodeIndex, NodeIndex, LinkDelay)>,
     pub arena: &'static Arena,
+    pub delays: DelayLibrary,
+    scopes: Vec<Scope>,
+    node_names: Vec<(NodeIndex, String)>,
 }
 
 impl<'a> NodeCreator<'a> {
 
     pub fn new(parent: &NodeCollection<'a>) -> NodeCreator<'a> {
+        NodeCreator::with_delays(parent, DelayLibrary::standard())
+    }
+    
+    /// How many nodes exist in the parent collection and this creator together.
+    pub fn node_count(&self) -> usize {
+        self.creation_index
+    }
+    
+    pub fn with_delays(parent: &NodeCollection<'a>, delays: DelayLibrary) -> NodeCreator<'a> {
         NodeCreator{
             creation_index: parent.nodes.len(),
             elements: Vec::new(),
             links: Vec::new(),
             arena: unsafe{ parent.static_arena_ref() },
+            delays: delays,
+            scopes: vec![Scope{ path: String::new(), child_counts: HashMap::new() }],
+            node_names: Vec::new(),
         }
     }
     
+    /// Everything created until the matching `end_component` is named as part of a new
+    /// instance of `kind`, such as "Adder3" inside "RippleCarryAdder0".
+    pub fn begin_component(&mut self, kind: &str) {
+        let path = {
+            let parent = self.scopes.last_mut().unwrap();
+            let instance = parent.child_counts.get(kind).map(|count| { *count }).unwrap_or(0);
+            parent.child_counts.insert(kind.to_string(), instance + 1);
+            
+            if parent.path.is_empty() {
+                format!("{}{}", kind, instance)
+            } else {
+                format!("{}/{}{}", parent.path, kind, instance)
+            }
+        };
+        
+        self.scopes.push(Scope{
+            path: path,
+            child_counts: HashMap::new(),
+        });
+    }
+    
+    pub fn end_component(&mut self) {
+        assert!(self.scopes.len() > 1, "end_component without a matching begin_component");
+        self.scopes.pop();
+    }
+    
+    /// A new node named after the component being built, like "Adder3/HalfAdder0/XorGate0/Nand1.a".
+    pub fn new_named_node(&mut self, port: &str) -> NodeIndex {
+        let node = self.new_node();
+        let name = format!("{}.{}", self.scopes.last().unwrap().path, port);
+        self.node_names.push((node, name));
+        node
+    }
+    
+    /// Names a node made with `new_node`.
+    pub fn name_node(&mut self, node: NodeIndex, name: &str) {
+        self.node_names.push((node, name.to_string()));
+    }
+    
+    pub fn node_names(&self) -> &[(NodeIndex, String)] {
+        &self.node_names[]
+    }
+    
+    pub fn elements(&self) -> &[&'a (Element + 'a)] {
+        &self.elements[]
+    }
+    
+    /// Every link made so far, with None as the delay of links made with `wire`.
+    pub fn links(&self) -> Vec<(NodeIndex, NodeIndex, Option<PropogationDelay>)> {
+        self.links.iter().map(|&(a, b, delay)| {
+            match delay {
+                LinkDelay::Fixed(delay) | LinkDelay::Exact(delay) => (a, b, Some(delay)),
+                LinkDelay::Wire => (a, b, None),
+            }
+        }).collect()
+    }
+    
     pub fn new_node(&mut self) -> NodeIndex {
         let ret = self.creation_index;
         self.creation_index += 1;
@@ -340,7 +1191,16 @@ impl<'a> NodeCreator<'a> {
         if a==b {
             return;
         }
-        self.links.push((a, b, delay));
+        self.links.push((a, b, LinkDelay::Fixed(delay)));
+    }
+    
+    /// Like `link`, but the collection's jitter leaves the delay alone, for delays that
+    /// have already been jittered.
+    pub fn link_exact(&mut self, a: NodeIndex, b: NodeIndex, delay: PropogationDelay) {
+        if a==b {
+            return;
+        }
+        self.links.push((a, b, LinkDelay::Exact(delay)));
     }
     
     pub fn multilink(&mut self, froms: &[NodeIndex], tos: &[NodeIndex], delay: PropogationDelay) {
@@ -354,6 +1214,202 @@ impl<'a> NodeCreator<'a> {
             self.link(from, *to, delay);
         }
     }
+    
+    /// Links two nodes with a wire, whose delay comes from the delay library.
+    pub fn wire(&mut self, a: NodeIndex, b: NodeIndex) {
+        if a==b {
+            return;
+        }
+        self.links.push((a, b, LinkDelay::Wire));
+    }
+    
+    pub fn multiwire(&mut self, froms: &[NodeIndex], tos: &[NodeIndex]) {
+        for (from, to) in froms.iter().zip(tos.iter()) {
+            self.wire(*from, *to);
+        }
+    }
+    
+    pub fn wire_one_to_many(&mut self, from: NodeIndex, tos: &[NodeIndex]) {
+        for to in tos.iter() {
+            self.wire(from, *to);
+        }
+    }
+    
+    /// How many wires touch each node, indexed by node.
+    fn wire_fanouts(&self) -> Vec<u32> {
+        let mut fanouts : Vec<u32> = range(0, self.creation_index).map(|_| { 0 }).collect();
+        for &(a, b, delay) in self.links.iter() {
+            if let LinkDelay::Wire = delay {
+                fanouts[a.get()] += 1;
+                fanouts[b.get()] += 1;
+            }
+        }
+        fanouts
+    }
 }
 
 pub const STANDARD_DELAY: PropogationDelay = PropogationDelay(100);
+
+/// Splits an integer into line states for a bus, least significant bit first.
+pub fn bus_states(value: u64, bit_count: usize) -> Vec<LineState> {
+    assert!(bit_count <= 64);
+    range(0, bit_count).map(|bit_index| {
+        if (value & (1 << bit_index)) != 0 { LineState::High } else { LineState::Low }
+    }).collect()
+}
+
+pub fn read_bus(nodes: &[NodeIndex], c: &NodeCollection) -> Option<u64> {
+    let states : Vec<LineState> = nodes.iter().map(|node| { node.read(c) }).collect();
+    bus_value(&states[])
+}
+
+/// Interprets a bus (least significant bit first) as an integer, if every line is Low or High
+/// and it has no more than 64 lines.
+pub fn bus_value(states: &[LineState]) -> Option<u64> {
+    if states.len() > 64 {
+        return None;
+    }
+    let mut accum = 0u64;
+    for (index, state) in states.iter().enumerate() {
+        match *state {
+            LineState::Low => {},
+            LineState::High => {
+                accum |= 1<<index;
+            },
+            _ => { return None; }
+        }
+    }
+    
+    Some(accum)
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    
+    use super::{NodeCollection, NodeCreator, LineState, PropogationDelay, bus_value};
+    use logic_gates::NotGate;
+    use pin::Pin;
+    
+    #[test]
+    fn test_bus_value() {
+        assert_eq!(bus_value(&[LineState::High, LineState::Low, LineState::High][]), Some(5));
+        assert_eq!(bus_value(&[LineState::High, LineState::Floating][]), None);
+        let mut wide = [LineState::Low; 64].to_vec();
+        wide[63] = LineState::High;
+        assert_eq!(bus_value(&wide[]), Some(1 << 63));
+        wide.push(LineState::Low);
+        assert_eq!(bus_value(&wide[]), None);
+    }
+    
+    #[test]
+    fn test_watch() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let not = NotGate::new(&mut creator);
+        let input = Pin::new(&mut creator);
+        creator.wire(input.node, not.input);
+        c.absorb(creator);
+        
+        input.node.write(LineState::Low, &mut c);
+        while c.play() {}
+        
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let recorder = changes.clone();
+        c.watch(not.output, move |tick, old, new| {
+            recorder.borrow_mut().push((tick, old, new));
+        });
+        
+        let start = c.current_tick;
+        input.node.write(LineState::High, &mut c);
+        while c.play() {}
+        
+        let changes = changes.borrow();
+        assert_eq!(changes.len(), 1);
+        let (tick, old, new) = changes[0];
+        assert_eq!((old, new), (LineState::High, LineState::Low));
+        assert!(tick > start);
+    }
+    
+    #[test]
+    fn test_force_mid_net() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let pin = Pin::new(&mut creator);
+        let near = creator.new_node();
+        let middle = creator.new_node();
+        let far = creator.new_node();
+        creator.link(pin.node, near, PropogationDelay(10));
+        creator.link(near, middle, PropogationDelay(10));
+        creator.link(middle, far, PropogationDelay(10));
+        c.absorb(creator);
+        
+        pin.node.write(LineState::High, &mut c);
+        while c.play() {}
+        assert_eq!(far.read(&c), LineState::High);
+        
+        // The forced node holds the far side of the net against the pin
+        c.force(middle, LineState::Low);
+        while c.play() {}
+        assert_eq!((near.read(&c), far.read(&c)), (LineState::High, LineState::Low));
+        pin.node.write(LineState::High, &mut c);
+        while c.play() {}
+        assert_eq!((near.read(&c), far.read(&c)), (LineState::High, LineState::Low));
+        
+        c.release(middle);
+        while c.play() {}
+        assert_eq!((middle.read(&c), far.read(&c)), (LineState::High, LineState::High));
+    }
+    
+    #[test]
+    fn test_step_back() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let not = NotGate::new(&mut creator);
+        let input = Pin::new(&mut creator);
+        creator.wire(input.node, not.input);
+        c.absorb(creator);
+        
+        input.node.write(LineState::Low, &mut c);
+        while c.play() {}
+        let start = c.current_tick;
+        let played_before = c.events_played();
+        let changes = Rc::new(RefCell::new(0));
+        let counter = changes.clone();
+        c.watch(not.output, move |_, _, _| { *counter.borrow_mut() += 1; });
+        
+        c.record_history(true);
+        input.node.write(LineState::High, &mut c);
+        while c.play() {}
+        assert_eq!(not.output.read(&c), LineState::Low);
+        let end = c.current_tick;
+        let played = c.events_played();
+        
+        c.step_back_to(start);
+        assert_eq!(c.current_tick, start);
+        assert_eq!(c.last_recorded_time(), Some(start));
+        assert_eq!(not.output.read(&c), LineState::High);
+        // Undoing is not a change watchers hear about
+        assert_eq!(*changes.borrow(), 1);
+        
+        while c.step_back() {}
+        assert_eq!(input.node.last_written(&c), LineState::Low);
+        assert_eq!(c.pending_events().len(), 0);
+        assert_eq!(c.events_played(), played_before);
+        
+        // Replaying gives the same result
+        input.node.write(LineState::High, &mut c);
+        while c.play() {}
+        assert_eq!(not.output.read(&c), LineState::Low);
+        assert_eq!((c.current_tick, c.events_played()), (end, played));
+        
+        assert!(c.history_len() > 2);
+        c.limit_history(Some(2));
+        assert_eq!(c.history_len(), 2);
+        input.node.write(LineState::Low, &mut c);
+        assert_eq!(c.history_len(), 2);
+        c.clear_history();
+        assert_eq!(c.history_len(), 0);
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+
+use sim::{NodeCollection, NodeCreator, NodeIndex, LineState, PropogationDelay, SavedState, SavedNode, SavedEvent};
+use primitive::{rebuild_element, primitive_node_count};
+
+const HEADER: &'static str = "logicsim-state";
+const VERSION: u32 = 1;
+
+// The file is one record per line:
+//   logicsim-state <version>
+//   state <tick> <event id counter> <force id counter> <events played> <peak pending events>
+//   node <index> <output state> <forced state or -> <events played> <toggles> <seen low> <seen high> [name]
+//   influence <node> <forcer> <state> <force id>
+//   element <kind> <delay percent> <node count> <nodes>... <params>...
+//   link <a> <b> <delay>
+//   event <node> <state> <time> <id> <forcer> <force id>
+// Nodes must come in index order, and each belongs to at most one element. States are
+// written 0, 1, z or x.
+
+pub fn format_state(state: LineState) -> &'static str {
+    match state {
+        LineState::Low => "0",
+        LineState::High => "1",
+        LineState::Floating => "z",
+        LineState::Conflict => "x",
+    }
+}
+
+fn parse_state(word: &str, line_number: usize) -> Result<LineState, String> {
+    match word {
+        "0" => Ok(LineState::Low),
+        "1" => Ok(LineState::High),
+        "z" => Ok(LineState::Floating),
+        "x" => Ok(LineState::Conflict),
+        _ => Err(format!("line {}: bad state {}", line_number, word)),
+    }
+}
+
+fn parse_number<T: FromStr>(word: &str, line_number: usize) -> Result<T, String> {
+    word.parse::<T>().map_err(|_| { format!("line {}: bad number {}", line_number, word) })
+}
+
+fn parse_node(word: &str, node_count: usize, line_number: usize) -> Result<NodeIndex, String> {
+    let index : usize = try!(parse_number(word, line_number));
+    if index >= node_count {
+        return Err(format!("line {}: no node {}", line_number, index));
+    }
+    Ok(NodeIndex(index))
+}
+
+/// Writes out everything needed to carry on simulating the collection somewhere else.
+/// Watchers and recorded history are not saved. Only collections of primitive elements,
+/// which `load` knows how to rebuild, can be saved.
+pub fn save(c: &NodeCollection) -> Result<String, String> {
+    if let Some(element) = c.elements().iter().find(|element| { primitive_node_count(element.kind()).is_none() }) {
+        return Err(format!("cannot save {} elements, only primitives", element.kind()));
+    }
+    
+    let state = c.save_state();
+    let mut text = format!("{} {}\n", HEADER, VERSION);
+    text.push_str(&format!("state {} {} {} {} {}\n", state.current_tick, state.event_id_counter, state.force_id_counter, state.events_played, state.peak_pending_events)[]);
+    
+    for (index, node) in state.nodes.iter().enumerate() {
+        text.push_str(&format!("node {} {} {} {} {} {} {}",
+            index,
+            format_state(node.output_state),
+            node.forced.map(|forced| { format_state(forced) }).unwrap_or("-"),
+            node.events_played,
+            node.toggles,
+            if node.seen_low { 1 } else { 0 },
+            if node.seen_high { 1 } else { 0 })[]);
+        if let Some(ref name) = node.name {
+            text.push_str(&format!(" {}", name)[]);
+        }
+        text.push_str("\n");
+        
+        for &(forcer, state, force_id) in node.influences.iter() {
+            text.push_str(&format!("influence {} {} {} {}\n", index, forcer.0, format_state(state), force_id)[]);
+        }
+    }
+    
+    for (element, percent) in c.elements().iter().zip(state.element_delay_percents.iter()) {
+        let nodes = element.get_nodes();
+        text.push_str(&format!("element {} {} {}", element.kind(), percent, nodes.len())[]);
+        for node in nodes.iter() {
+            text.push_str(&format!(" {}", node.0)[]);
+        }
+        for param in element.params().iter() {
+            text.push_str(&format!(" {}", param)[]);
+        }
+        text.push_str("\n");
+    }
+    
+    for &(a, b, delay) in c.links().iter() {
+        text.push_str(&format!("link {} {} {}\n", a.0, b.0, delay.0)[]);
+    }
+    
+    for evt in state.events.iter() {
+        text.push_str(&format!("event {} {} {} {} {} {}\n", evt.node.0, format_state(evt.new_state), evt.time, evt.id, evt.forcer.0, evt.force_id)[]);
+    }
+    Ok(text)
+}
+
+/// Rebuilds a saved collection inside `c`, which must be empty.
+pub fn load(text: &str, c: &mut NodeCollection) -> Result<(), String> {
+    if c.node_count() != 0 {
+        return Err("can only load into an empty collection".to_string());
+    }
+    
+    let mut lines = text.lines().enumerate().map(|(index, line)| { (index + 1, line.trim()) }).filter(|&(_, line)| { !line.is_empty() });
+    match lines.next() {
+        Some((_, header)) => {
+            let words : Vec<&str> = header.split(' ').collect();
+            if words.len() != 2 || words[0] != HEADER {
+                return Err("not a saved collection".to_string());
+            }
+            if words[1] != format!("{}", VERSION) {
+                return Err(format!("unsupported version {}", words[1]));
+            }
+        },
+        None => { return Err("empty file".to_string()); }
+    }
+    
+    let mut state = SavedState {
+        current_tick: 0,
+        event_id_counter: 0,
+        force_id_counter: 0,
+        events_played: 0,
+        peak_pending_events: 0,
+        nodes: Vec::new(),
+        events: Vec::new(),
+        element_delay_percents: Vec::new(),
+    };
+    let mut influences = Vec::new();
+    let mut elements = Vec::new();
+    let mut links = Vec::new();
+    
+    for (line_number, line) in lines {
+        let words : Vec<&str> = line.split(' ').filter(|word| { !word.is_empty() }).collect();
+        match words[0] {
+            "state" if words.len() == 6 => {
+                state.current_tick = try!(parse_number(words[1], line_number));
+                state.event_id_counter = try!(parse_number(words[2], line_number));
+                state.force_id_counter = try!(parse_number(words[3], line_number));
+                state.events_played = try!(parse_number(words[4], line_number));
+                state.peak_pending_events = try!(parse_number(words[5], line_number));
+            },
+            "node" if words.len() >= 8 => {
+                let index : usize = try!(parse_number(words[1], line_number));
+                if index != state.nodes.len() {
+                    return Err(format!("line {}: expected node {}", line_number, state.nodes.len()));
+                }
+                state.nodes.push(SavedNode {
+                    name: if words.len() > 8 { Some(words[8..].connect(" ")) } else { None },
+                    output_state: try!(parse_state(words[2], line_number)),
+                    forced: if words[3] == "-" { None } else { Some(try!(parse_state(words[3], line_number))) },
+                    influences: Vec::new(),
+                    events_played: try!(parse_number(words[4], line_number)),
+                    toggles: try!(parse_number(words[5], line_number)),
+                    seen_low: words[6] == "1",
+                    seen_high: words[7] == "1",
+                });
+            },
+            "influence" if words.len() == 5 => {
+                influences.push((line_number, words[1], words[2], try!(parse_state(words[3], line_number)), try!(parse_number::<u64>(words[4], line_number))));
+            },
+            "element" if words.len() >= 4 => {
+                let node_count : usize = try!(parse_number(words[3], line_number));
+                if words.len() < 4 + node_count {
+                    return Err(format!("line {}: expected {} nodes", line_number, node_count));
+                }
+                state.element_delay_percents.push(try!(parse_number(words[2], line_number)));
+                elements.push((line_number, words[1], words[4..4 + node_count].to_vec(), words[4 + node_count..].to_vec()));
+            },
+            "link" if words.len() == 4 => {
+                links.push((line_number, words[1], words[2], try!(parse_number::<u32>(words[3], line_number))));
+            },
+            "event" if words.len() == 7 => {
+                let node_count = state.nodes.len();
+                state.events.push(SavedEvent {
+                    node: try!(parse_node(words[1], node_count, line_number)),
+                    new_state: try!(parse_state(words[2], line_number)),
+                    time: try!(parse_number(words[3], line_number)),
+                    id: try!(parse_number(words[4], line_number)),
+                    forcer: try!(parse_node(words[5], node_count, line_number)),
+                    force_id: try!(parse_number(words[6], line_number)),
+                });
+            },
+            _ => {
+                return Err(format!("line {}: cannot read {}", line_number, line));
+            }
+        }
+    }
+    
+    let node_count = state.nodes.len();
+    for &(line_number, node, forcer, influence_state, force_id) in influences.iter() {
+        let node = try!(parse_node(node, node_count, line_number));
+        let forcer = try!(parse_node(forcer, node_count, line_number));
+        state.nodes[node.0].influences.push((forcer, influence_state, force_id));
+    }
+    
+    let mut creator = NodeCreator::new(c);
+    while creator.node_count() < node_count {
+        creator.new_node();
+    }
+    let mut owners : HashMap<usize, usize> = HashMap::new(); // The line of the element each node belongs to
+    for &(line_number, kind, ref node_words, ref param_words) in elements.iter() {
+        let mut nodes = Vec::new();
+        for word in node_words.iter() {
+            let node = try!(parse_node(*word, node_count, line_number));
+            if let Some(owner) = owners.get(&node.0) {
+                return Err(format!("line {}: node {} is already a node of the element on line {}", line_number, node.0, owner));
+            }
+            owners.insert(node.0, line_number);
+            nodes.push(node);
+        }
+        let mut params = Vec::new();
+        for word in param_words.iter() {
+            params.push(try!(parse_number::<u64>(*word, line_number)));
+        }
+        try!(rebuild_element(kind, &nodes[], &params[], &mut creator).map_err(|message| {
+            format!("line {}: {}", line_number, message)
+        }));
+    }
+    for &(line_number, a, b, delay) in links.iter() {
+        let a = try!(parse_node(a, node_count, line_number));
+        let b = try!(parse_node(b, node_count, line_number));
+        // The saved delays were already jittered, if they ever were
+        creator.link_exact(a, b, PropogationDelay(delay));
+    }
+    
+    c.absorb(creator);
+    c.restore_state(state);
+    Ok(())
+}
+
+pub fn save_to_file(c: &NodeCollection, path: &Path) -> Result<(), String> {
+    let text = try!(save(c));
+    File::create(path).and_then(|mut file| { file.write_all(text.as_bytes()) }).map_err(|error| {
+        format!("cannot write {}: {}", path.display(), error)
+    })
+}
+
+pub fn load_from_file(path: &Path, c: &mut NodeCollection) -> Result<(), String> {
+    let mut text = String::new();
+    try!(File::open(path).and_then(|mut file| { file.read_to_string(&mut text) }).map_err(|error| {
+        format!("cannot read {}: {}", path.display(), error)
+    }));
+    load(&text[], c)
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, NodeIndex, LineState, Element};
+    use jitter::Jitter;
+    use adder::Adder;
+    use storage::Register;
+    use clock::{Clock, ClockParams};
+    use rom::ConstantBits;
+    use pin::Pin;
+    use super::{save, load};
+    
+    #[test]
+    fn test_save_mid_simulation() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let clock = Clock::new(ClockParams::square(4000), &mut creator);
+        let register = Register::new(&mut creator, 4);
+        let value = ConstantBits::new(&ConstantBits::make_bits(9, 4)[], &mut creator);
+        let reset = Pin::new(&mut creator);
+        creator.wire(clock.node, register.clock);
+        creator.multiwire(&value.bits[], &register.inputs[]);
+        c.absorb(creator);
+        reset.node.write(LineState::Low, &mut c);
+        
+        c.play_until(2100);
+        let saved = save(&c).unwrap();
+        
+        let mut loaded = NodeCollection::new();
+        load(&saved[], &mut loaded).unwrap();
+        assert_eq!(save(&loaded).unwrap(), saved);
+        assert_eq!(loaded.find_node("Pin0.node"), Some(reset.node));
+        
+        // Both copies carry on the same way
+        c.play_until(9000);
+        loaded.play_until(9000);
+        assert_eq!(register.read_u64(&loaded), Some(9));
+        assert_eq!(save(&loaded), save(&c));
+    }
+    
+    #[test]
+    fn test_keeps_jittered_delays() {
+        let mut c = NodeCollection::new();
+        c.set_jitter(Jitter { seed: 7, spread_percent: 50 });
+        let mut creator = NodeCreator::new(&c);
+        let adder = Adder::new(&mut creator);
+        let input = Pin::new(&mut creator);
+        creator.wire(input.node, adder.a);
+        c.absorb(creator);
+        let saved = save(&c).unwrap();
+        
+        // Loading into a collection with other jitter keeps the delays that were saved
+        let mut loaded = NodeCollection::new();
+        loaded.set_jitter(Jitter { seed: 8, spread_percent: 50 });
+        load(&saved[], &mut loaded).unwrap();
+        assert_eq!(save(&loaded).unwrap(), saved);
+    }
+    
+    struct Custom {
+        node: NodeIndex,
+    }
+    
+    impl Element for Custom {
+        fn step(&self, _: &mut NodeCollection) {}
+        fn get_nodes(&self) -> Vec<NodeIndex> { vec![self.node] }
+        fn kind(&self) -> &'static str { "Custom" }
+    }
+    
+    #[test]
+    fn test_rejects_elements_it_cannot_load() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let node = creator.new_node();
+        let elem = creator.arena.alloc(|| { Custom { node: node } });
+        creator.add_element(elem);
+        c.absorb(creator);
+        assert_eq!(save(&c), Err("cannot save Custom elements, only primitives".to_string()));
+    }
+    
+    #[test]
+    fn test_rejects_other_versions() {
+        let mut c = NodeCollection::new();
+        assert!(load("logicsim-state 2\n", &mut c).is_err());
+        assert!(load("logicsim-state 1\nnode 0 0 - 0 0 0 0\nevent 3 1 0 1 0 1\n", &mut c).is_err());
+    }
+    
+    #[test]
+    fn test_rejects_bad_elements() {
+        let mut c = NodeCollection::new();
+        let nodes = "logicsim-state 1\nnode 0 0 - 0 0 0 0\nnode 1 0 - 0 0 0 0\nnode 2 0 - 0 0 0 0\n";
+        assert_eq!(load(&format!("{}element Pin 100 1 0\nelement Nand 100 3 1 2 0 0\n", nodes)[], &mut c),
+            Err("line 6: node 0 is already a node of the element on line 5".to_string()));
+        assert_eq!(load(&format!("{}element Nand 100 3 0 1 2 4294967296\n", nodes)[], &mut c),
+            Err("line 5: Nand delay 4294967296 does not fit in 32 bits".to_string()));
+        assert_eq!(load(&format!("{}element Clock 100 1 0 4294967296 100 0\n", nodes)[], &mut c),
+            Err("line 5: Clock period and phase must fit in 32 bits".to_string()));
+        assert_eq!(c.node_count(), 0);
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use time::precise_time_ns;
+
+use sim::{NodeCollection, NodeIndex};
+
+/// What happened during one stretch of simulation.
+pub struct Stats {
+    pub events_played: u64,
+    pub events_per_node: Vec<u64>,
+    pub toggles_per_node: Vec<u64>,
+    pub peak_pending_events: usize,
+    pub elements_by_kind: Vec<(&'static str, usize)>,
+    pub simulated_ticks: u64,
+    pub wall_seconds: f64,
+}
+
+impl Stats {
+    /// How fast the simulation ran, or 0 if it was over too quickly to time.
+    pub fn ticks_per_second(&self) -> f64 {
+        if self.wall_seconds > 0.0 {
+            (self.simulated_ticks as f64) / self.wall_seconds
+        } else {
+            0.0
+        }
+    }
+    
+    /// The total number of Low/High transitions, a rough stand-in for switching power.
+    pub fn total_toggles(&self) -> u64 {
+        self.toggles_per_node.iter().fold(0, |sum, toggles| { sum + *toggles })
+    }
+    
+    /// The `count` nodes that toggled most, busiest first.
+    pub fn busiest_nodes(&self, count: usize) -> Vec<(NodeIndex, u64)> {
+        let mut nodes : Vec<(NodeIndex, u64)> = self.toggles_per_node.iter().enumerate().map(|(index, toggles)| {
+            (NodeIndex(index), *toggles)
+        }).collect();
+        nodes.sort_by(|a, b| { b.1.cmp(&a.1) });
+        nodes.truncate(count);
+        nodes
+    }
+    
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        report.push_str(&format!("events played: {}\n", self.events_played)[]);
+        report.push_str(&format!("total toggles: {}\n", self.total_toggles())[]);
+        report.push_str(&format!("peak pending events: {}\n", self.peak_pending_events)[]);
+        report.push_str(&format!("simulated {} ticks in {:.3}s ({:.0} ticks/s)\n", self.simulated_ticks, self.wall_seconds, self.ticks_per_second())[]);
+        report.push_str("elements:\n");
+        for &(kind, count) in self.elements_by_kind.iter() {
+            report.push_str(&format!("  {}: {}\n", kind, count)[]);
+        }
+        report.push_str("busiest nodes:\n");
+        for &(node, toggles) in self.busiest_nodes(10).iter() {
+            report.push_str(&format!("  {:?}: {} toggles, {} events\n", node, toggles, self.events_per_node[node.0])[]);
+        }
+        report
+    }
+}
+
+fn count_kinds(c: &NodeCollection) -> Vec<(&'static str, usize)> {
+    let mut counts : HashMap<&'static str, usize> = HashMap::new();
+    for kind in c.element_kinds().into_iter() {
+        let count = counts.get(&kind).map(|count| { *count }).unwrap_or(0);
+        counts.insert(kind, count + 1);
+    }
+    
+    let mut counts : Vec<(&'static str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| { a.0.cmp(&b.0) });
+    counts
+}
+
+/// Plays until no events are left, and reports on just that stretch of simulation.
+pub fn play_with_stats(c: &mut NodeCollection) -> Stats {
+    let node_count = c.node_count();
+    let events_before : Vec<u64> = range(0, node_count).map(|index| { c.node_events_played(NodeIndex(index)) }).collect();
+    let toggles_before : Vec<u64> = range(0, node_count).map(|index| { c.node_toggles(NodeIndex(index)) }).collect();
+    let played_before = c.events_played();
+    let start_tick = c.current_tick;
+    c.reset_peak_pending_events();
+    
+    let started = precise_time_ns();
+    while c.play() {}
+    let elapsed = precise_time_ns() - started;
+    
+    Stats {
+        events_played: c.events_played() - played_before,
+        events_per_node: events_before.iter().enumerate().map(|(index, before)| { c.node_events_played(NodeIndex(index)) - *before }).collect(),
+        toggles_per_node: toggles_before.iter().enumerate().map(|(index, before)| { c.node_toggles(NodeIndex(index)) - *before }).collect(),
+        peak_pending_events: c.peak_pending_events(),
+        elements_by_kind: count_kinds(c),
+        simulated_ticks: c.current_tick - start_tick,
+        wall_seconds: (elapsed as f64) / 1e9,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay};
+    use logic_gates::NotGate;
+    use pin::Pin;
+    use super::play_with_stats;
+    
+    #[test]
+    fn test_counts_toggles() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let not = NotGate::new(&mut creator);
+        let input = Pin::new(&mut creator);
+        creator.wire(input.node, not.input);
+        c.absorb(creator);
+        
+        input.node.write(LineState::Low, &mut c);
+        while c.play() {}
+        
+        input.node.write(LineState::High, &mut c);
+        let stats = play_with_stats(&mut c);
+        
+        assert_eq!(stats.toggles_per_node[not.output.0], 1);
+        assert_eq!(stats.toggles_per_node[not.input.0], 1);
+        assert_eq!(stats.elements_by_kind, [("Nand", 1), ("Pin", 1)].to_vec());
+        assert!(stats.events_played > 0);
+        assert!(stats.ticks_per_second() >= 0.0);
+        
+        // The peak covers only the stretch played, not the pile of writes before it
+        for delay in range(1, 20) {
+            let state = if delay % 2 == 0 { LineState::Low } else { LineState::High };
+            input.node.write_later(state, PropogationDelay(delay * 1000), &mut c);
+        }
+        while c.play() {}
+        assert!(c.peak_pending_events() >= 19);
+        input.node.write(LineState::Low, &mut c);
+        assert!(play_with_stats(&mut c).peak_pending_events < 19);
+    }
+}
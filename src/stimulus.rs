@@ -0,0 +1,90 @@
+use std::u32;
+
+use sim::{NodeIndex, NodeCollection, LineState, PropogationDelay, bus_states};
+
+/// A timeline of writes to input pins, scheduled all at once with `apply`.
+/// Ticks are relative to the collection's current tick when the stimulus is applied, and
+/// must fit in a delay.
+pub struct Stimulus {
+    entries: Vec<(u64, NodeIndex, LineState)>,
+}
+
+impl Stimulus {
+    pub fn new() -> Stimulus {
+        Stimulus {
+            entries: Vec::new(),
+        }
+    }
+    
+    pub fn at(&mut self, tick: u64, node: NodeIndex, state: LineState) -> &mut Stimulus {
+        assert!(tick <= u32::MAX as u64, "stimulus ticks must fit in a delay");
+        self.entries.push((tick, node, state));
+        self
+    }
+    
+    /// Writes `value` onto a bus, least significant bit first.
+    pub fn bus_at(&mut self, tick: u64, nodes: &[NodeIndex], value: u64) -> &mut Stimulus {
+        assert!(tick <= u32::MAX as u64, "stimulus ticks must fit in a delay");
+        for (node, state) in nodes.iter().zip(bus_states(value, nodes.len()).into_iter()) {
+            self.entries.push((tick, *node, state));
+        }
+        self
+    }
+    
+    pub fn apply(&self, c: &mut NodeCollection) {
+        // A write scheduled later overrides every earlier-scheduled write from the same node, no
+        // matter when each lands, so the writes must be scheduled in the order they take effect.
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| { a.0.cmp(&b.0) });
+        
+        for &(tick, node, state) in entries.iter() {
+            node.write_later(state, PropogationDelay(tick as u32), c);
+        }
+    }
+    
+    /// The tick of the last entry, relative to when the stimulus is applied.
+    pub fn duration(&self) -> u64 {
+        self.entries.iter().map(|entry| { entry.0 }).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, read_bus};
+    use logic_gates::XorGateVec;
+    use pin::Pin;
+    use super::Stimulus;
+    
+    #[test]
+    fn test_bus_stimulus() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let xors = XorGateVec::new(4, &mut creator);
+        let a : Vec<Pin> = range(0, 4).map(|_| { Pin::new(&mut creator) }).collect();
+        let b : Vec<Pin> = range(0, 4).map(|_| { Pin::new(&mut creator) }).collect();
+        for (pin, input) in a.iter().zip(xors.a.iter()) {
+            creator.wire(pin.node, *input);
+        }
+        for (pin, input) in b.iter().zip(xors.b.iter()) {
+            creator.wire(pin.node, *input);
+        }
+        c.absorb(creator);
+        
+        let a_nodes : Vec<_> = a.iter().map(|pin| { pin.node }).collect();
+        let b_nodes : Vec<_> = b.iter().map(|pin| { pin.node }).collect();
+        
+        let mut stimulus = Stimulus::new();
+        stimulus.bus_at(2000, &b_nodes[], 0b0110)
+            .bus_at(0, &a_nodes[], 0b1010)
+            .bus_at(0, &b_nodes[], 0b0000)
+            .bus_at(4000, &a_nodes[], 0b1111);
+        stimulus.apply(&mut c);
+        
+        c.play_until(1500);
+        assert_eq!(read_bus(&xors.output[], &c), Some(0b1010));
+        c.play_until(3500);
+        assert_eq!(read_bus(&xors.output[], &c), Some(0b1100));
+        c.play_until(5500);
+        assert_eq!(read_bus(&xors.output[], &c), Some(0b1001));
+    }
+}
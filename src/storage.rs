@@ -1,27 +1,19 @@
 
-use sim::{LineState, NodeIndex, NodeCreator, NodeCollection, PropogationDelay, STANDARD_DELAY};
+use sim::{NodeIndex, NodeCreator, NodeCollection, PropogationDelay, read_bus};
 
 use logic_gates::{NandGate, AndGate};
 
 use arena::Arena;
 
-#[derive(Debug)]
-pub struct NotSRLatch {
-    pub not_s: NodeIndex,
-    pub not_r: NodeIndex,
-    pub q: NodeIndex,
-    pub not_q: NodeIndex,
-}
-
-impl NotSRLatch {
-    pub fn new(creator: &mut NodeCreator) -> NotSRLatch {
-        let top = NandGate::new(creator);
-        let bottom = NandGate::new(creator);
-        
-        creator.link(top.output, bottom.a, STANDARD_DELAY);
-        creator.link(bottom.output, top.b, STANDARD_DELAY);
-        
-        NotSRLatch{
+component! {
+    #[derive(Debug)]
+    pub struct NotSRLatch {
+        parts { top: NandGate, bottom: NandGate }
+        wires {
+            top.output -- bottom.a,
+            bottom.output -- top.b,
+        }
+        ports {
             not_s: top.a,
             not_r: bottom.b,
             q: top.output,
@@ -30,39 +22,25 @@ impl NotSRLatch {
     }
 }
 
-
-#[derive(Debug)]
-pub struct DFlipFlop {
-    pub clock: NodeIndex,
-    pub data: NodeIndex,
-    pub q: NodeIndex,
-    pub not_q: NodeIndex,
-}
-
-impl DFlipFlop {
-    pub fn new(creator: &mut NodeCreator) -> DFlipFlop {
-        let top = NotSRLatch::new(creator);
-        let bottom = NotSRLatch::new(creator);
-        let output = NotSRLatch::new(creator);
-        let ander = AndGate::new(creator);
-        
-        let clock = ander.a;
-        
-        creator.link(ander.b, top.not_q, STANDARD_DELAY);
-        creator.link(ander.output, bottom.not_s, STANDARD_DELAY);
-        let data = bottom.not_r;
-        
-        creator.link(bottom.q, output.not_r, STANDARD_DELAY);
-        creator.link(clock, top.not_r, STANDARD_DELAY);
-        creator.link(bottom.not_q, top.not_s, STANDARD_DELAY);
-        creator.link(top.not_q, output.not_s, STANDARD_DELAY);
-        
-        DFlipFlop {
-            clock: clock,
-            data: data,
+component! {
+    #[derive(Debug)]
+    pub struct DFlipFlop {
+        parts { top: NotSRLatch, bottom: NotSRLatch, output: NotSRLatch, ander: AndGate }
+        wires {
+            ander.b -- top.not_q,
+            ander.output -- bottom.not_s,
+            bottom.q -- output.not_r,
+            ander.a -- top.not_r,
+            bottom.not_q -- top.not_s,
+            top.not_q -- output.not_s,
+        }
+        ports {
+            clock: ander.a,
+            data: bottom.not_r,
             q: output.q,
-            not_q: output.not_q
+            not_q: output.not_q,
         }
+        unused { top.q }
     }
 }
 
@@ -74,13 +52,15 @@ pub struct Register {
 
 impl Register {
     pub fn new(creator: &mut NodeCreator, bit_count: usize) -> Register {
+        creator.begin_component("Register");
         let bits : Vec<DFlipFlop> = range(0, bit_count).map(|_| { DFlipFlop::new(creator) }).collect();
         
         let clock = bits[0].clock;
         for bit in (&bits[1..]).iter() {
-            creator.link(clock, bit.clock, STANDARD_DELAY);
+            creator.wire(clock, bit.clock);
         }
         
+        creator.end_component();
         Register {
             inputs: bits.iter().map(|bit| { return bit.data }).collect(),
             outputs: bits.iter().map(|bit| { return bit.q }).collect(),
@@ -89,17 +69,6 @@ impl Register {
     }
     
     pub fn read_u64(&self, c: &NodeCollection) -> Option<u64> {
-        let mut accum = 0u64;
-        for (index, output) in self.outputs.iter().enumerate() {
-            match output.read(c) {
-                LineState::Low => {},
-                LineState::High => {
-                    accum |= 1<<index;
-                },
-                _ => { return None; }
-            }
-        }
-        
-        return Some(accum);
+        read_bus(&self.outputs[], c)
     }
 }
@@ -1,25 +1,58 @@
 
-use sim::{NodeCreator, NodeIndex, STANDARD_DELAY, NodeCollection, LineState};
+use sim::{NodeCreator, NodeIndex, NodeCollection, LineState, DelayLibrary};
 use pin::Pin;
+use coverage::Coverage;
+use waveform::{Trace, render_ascii};
 
 pub fn check_truth_table<F>(f: F, cases: &[(&[u8],&[u8])]) 
     where F: FnOnce(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
+{
+    check_truth_table_with_delays(DelayLibrary::standard(), f, cases);
+}
+
+pub fn check_truth_table_with_delays<F>(delays: DelayLibrary, f: F, cases: &[(&[u8],&[u8])]) 
+    where F: FnOnce(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
+{
+    run_truth_table(delays, f, cases, None);
+}
+
+/// Checks the truth table, and adds every node's states during the run to `coverage`.
+pub fn check_truth_table_with_coverage<F>(coverage: &mut Coverage, f: F, cases: &[(&[u8],&[u8])]) 
+    where F: FnOnce(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
+{
+    run_truth_table(DelayLibrary::standard(), f, cases, Some(coverage));
+}
+
+fn run_truth_table<F>(delays: DelayLibrary, f: F, cases: &[(&[u8],&[u8])], coverage: Option<&mut Coverage>) 
+    where F: FnOnce(&mut NodeCreator) -> (Vec<NodeIndex>, Vec<NodeIndex>)
 {
     let mut c = NodeCollection::new();
-    let mut creator = NodeCreator::new(&c);
+    let mut creator = NodeCreator::with_delays(&c, delays);
     
     let (inputs, outputs) = f(&mut creator);
     let input_pins : Vec<Pin> = inputs.iter().map(|input| {
         let p = Pin::new(&mut creator);
-        creator.link(*input, p.node, STANDARD_DELAY);
+        creator.wire(*input, p.node);
         p
     }).collect();
     
     c.absorb(creator);
     
+    // Kept so a failing case can show how its outputs got where they did
+    let mut trace = Trace::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let name = format!("in{} {}", index, c.node_name(*input));
+        trace.record(&name[], &[*input], &mut c);
+    }
+    for (index, output) in outputs.iter().enumerate() {
+        let name = format!("out{} {}", index, c.node_name(*output));
+        trace.record(&name[], &[*output], &mut c);
+    }
+    
     for (case_number, &(input_values, output_values)) in cases.iter().enumerate() {
         assert!(input_values.len() == input_pins.len());
         assert!(output_values.len() == outputs.len());
+        let case_start = c.current_tick;
         for (input_pin, input_value) in input_pins.iter().zip(input_values.iter()) {
             assert!(*input_value==0 || *input_value==1);
             input_pin.node.write(if *input_value==1 { LineState::High } else { LineState::Low }, &mut c);
@@ -40,6 +73,15 @@ pub fn check_truth_table<F>(f: F, cases: &[(&[u8],&[u8])])
         }).collect();
         
         let expected = output_values.to_vec();
-        assert!(actual==expected, "Case #{}. For inputs {:?}, expected and actual:\n{:?}\n{:?}", case_number+1, input_values.to_vec(), expected, actual);
+        if actual != expected {
+            let case_end = if c.current_tick > case_start { c.current_tick } else { case_start + 1 };
+            let ticks_per_column = (case_end - case_start + 59) / 60;
+            panic!("Case #{}. For inputs {:?}, expected and actual:\n{:?}\n{:?}\n{}", case_number+1, input_values.to_vec(), expected, actual,
+                render_ascii(&trace, case_start, case_end, ticks_per_column));
+        }
+    }
+    
+    if let Some(coverage) = coverage {
+        coverage.record(&c);
     }
-} 
+}
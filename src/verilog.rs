@@ -0,0 +1,985 @@
+use std::collections::HashMap;
+
+use sim::{NodeCreator, NodeIndex};
+use netlist::NetNames;
+use logic_gates::{binary_gate, invert};
+use rom::ConstantBit;
+
+/// Writes everything added to the creator as a structural Verilog module of nand primitives.
+/// Each port is a name and its nodes, least significant bit first. Link delays are left out,
+/// since Verilog wires have none, but NAND delays are kept. Outputs may share a net with
+/// other ports, but inputs may not share one with each other.
+pub fn write_verilog(module_name: &str, creator: &NodeCreator, inputs: &[(&str, &[NodeIndex])], outputs: &[(&str, &[NodeIndex])]) -> Result<String, String> {
+    let mut net_names = NetNames::new(creator);
+    let mut declarations = Vec::new();
+    let mut assigns = Vec::new();
+    let mut gates = Vec::new();
+    
+    let ports = inputs.iter().map(|port| { ("input", port) }).chain(outputs.iter().map(|port| { ("output", port) }));
+    for (direction, &(name, nodes)) in ports {
+        if nodes.len() == 1 {
+            declarations.push(format!("  {} {};", direction, name));
+        } else {
+            declarations.push(format!("  {} [{}:0] {};", direction, nodes.len() - 1, name));
+        }
+        
+        for (bit, node) in nodes.iter().enumerate() {
+            let bit_name = if nodes.len() == 1 { name.to_string() } else { format!("{}[{}]", name, bit) };
+            if let Some(existing) = net_names.name_port_bit(*node, bit_name.clone()) {
+                // Inputs come first, so only an input can alias another input, and it cannot be assigned to
+                if direction == "input" {
+                    return Err(format!("inputs {} and {} are on the same net", existing, bit_name));
+                }
+                assigns.push(format!("  assign {} = {};", bit_name, existing));
+            }
+        }
+    }
+    
+    {
+        let mut name_of = |node: NodeIndex| -> String { net_names.name(node) };
+        
+        for (index, element) in creator.elements().iter().enumerate() {
+            let nodes = element.get_nodes();
+            let params = element.params();
+            match element.kind() {
+                "Nand" => {
+                    let delay = if params[0] > 0 { format!(" #{}", params[0]) } else { String::new() };
+                    gates.push(format!("  nand{} g{}({}, {}, {});", delay, index, name_of(nodes[2]), name_of(nodes[0]), name_of(nodes[1])));
+                },
+                "ConstantBit" => {
+                    assigns.push(format!("  assign {} = 1'b{};", name_of(nodes[0]), params[0]));
+                },
+                "Pin" => {}, // Only driven from outside, which the ports already cover
+                kind => {
+                    return Err(format!("{} elements have no Verilog equivalent", kind));
+                }
+            }
+        }
+    }
+    
+    let port_names : Vec<&str> = inputs.iter().chain(outputs.iter()).map(|&(name, _)| { name }).collect();
+    let mut text = format!("module {}({});\n", module_name, port_names.connect(", "));
+    for declaration in declarations.iter() {
+        text.push_str(&declaration[]);
+        text.push_str("\n");
+    }
+    if !net_names.internal.is_empty() {
+        text.push_str(&format!("  wire {};\n", net_names.internal.connect(", "))[]);
+    }
+    for line in gates.iter().chain(assigns.iter()) {
+        text.push_str(&line[]);
+        text.push_str("\n");
+    }
+    text.push_str("endmodule\n");
+    Ok(text)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Symbol(char),
+}
+
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars : Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && i + 1 < chars.len() && chars[i + 1] == '/') {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\\' {
+            // An escaped identifier runs until whitespace.
+            let start = i + 1;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().map(|c| { *c }).collect()), line));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().map(|c| { *c }).collect()), line));
+        } else if c.is_numeric() || c == '\'' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '\'') {
+                i += 1;
+            }
+            tokens.push((Token::Number(chars[start..i].iter().map(|c| { *c }).filter(|c| { *c != '_' }).collect()), line));
+        } else if "()[]{}:;,.=&|^~#".contains_char(c) {
+            tokens.push((Token::Symbol(c), line));
+            i += 1;
+        } else {
+            return Err(format!("line {}: unexpected character {}", line, c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads "3", "4'b1010" or "8'hff" as a width, if it has one, and a value.
+fn parse_number(text: &str, line: usize) -> Result<(Option<usize>, u64), String> {
+    let (width, radix, digits) = match text.find('\'') {
+        Some(quote) => {
+            let width = if quote == 0 {
+                None
+            } else {
+                Some(try!(text[..quote].parse::<usize>().map_err(|_| { format!("line {}: bad width in {}", line, text) })))
+            };
+            let radix = match text[quote + 1..].chars().next() {
+                Some('b') | Some('B') => 2,
+                Some('o') | Some('O') => 8,
+                Some('d') | Some('D') => 10,
+                Some('h') | Some('H') => 16,
+                _ => { return Err(format!("line {}: bad base in {}", line, text)); }
+            };
+            (width, radix, &text[quote + 2..])
+        },
+        None => (None, 10, text),
+    };
+    
+    if digits.is_empty() {
+        return Err(format!("line {}: bad number {}", line, text));
+    }
+    let mut value : u64 = 0;
+    for digit in digits.chars() {
+        match digit.to_digit(radix) {
+            Some(digit) => {
+                value = match value.checked_mul(radix as u64).and_then(|value| { value.checked_add(digit as u64) }) {
+                    Some(value) => value,
+                    None => { return Err(format!("line {}: {} does not fit in 64 bits", line, text)); }
+                };
+            },
+            None => { return Err(format!("line {}: cannot use {} in {}", line, digit, text)); }
+        }
+    }
+    Ok((width, value))
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Signal(String, Option<(usize, usize)>), // A whole signal, or the bits from msb down to lsb
+    Constant(Vec<bool>), // Least significant bit first
+    Not(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Concat(Vec<Expr>), // Most significant part first, as written
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SignalKind {
+    Input,
+    Output,
+    Wire,
+}
+
+#[derive(Debug, Clone)]
+struct Signal {
+    name: String,
+    kind: SignalKind,
+    lsb: usize,
+    width: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Connection {
+    Named(String, Option<Expr>),
+    Positional(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Assign(Expr, Expr, usize),
+    Gate(String, Vec<Expr>, usize), // The kind, then the terminals with the outputs first
+    Instance(String, Vec<Connection>, usize), // The module instantiated
+}
+
+#[derive(Debug, Clone)]
+struct Module {
+    name: String,
+    ports: Vec<String>,
+    signals: Vec<Signal>,
+    statements: Vec<Statement>,
+}
+
+impl Module {
+    fn signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|signal| { signal.name == name })
+    }
+    
+    fn declare(&mut self, kind: SignalKind, range: Option<(usize, usize)>, name: String) {
+        let (lsb, width) = match range {
+            Some((msb, lsb)) if msb >= lsb => (lsb, msb - lsb + 1),
+            Some((msb, lsb)) => (msb, lsb - msb + 1),
+            None => (0, 1),
+        };
+        
+        // "output y;" and "wire y;" may both declare the same signal.
+        if let Some(existing) = self.signals.iter_mut().find(|signal| { signal.name == name }) {
+            if kind != SignalKind::Wire {
+                existing.kind = kind;
+            }
+            if range.is_some() {
+                existing.lsb = lsb;
+                existing.width = width;
+            }
+            return;
+        }
+        self.signals.push(Signal {
+            name: name,
+            kind: kind,
+            lsb: lsb,
+            width: width,
+        });
+    }
+}
+
+const GATE_KINDS: &'static [&'static str] = &["and", "nand", "or", "nor", "xor", "xnor", "not", "buf"];
+const UNSUPPORTED_KEYWORDS: &'static [&'static str] = &["reg", "inout", "always", "initial", "parameter", "localparam", "function", "task", "generate"];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        if self.position < self.tokens.len() {
+            self.tokens[self.position].1
+        } else {
+            self.tokens.last().map(|&(_, line)| { line }).unwrap_or(1)
+        }
+    }
+    
+    fn error<T>(&self, message: String) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line(), message))
+    }
+    
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|&(ref token, _)| { token })
+    }
+    
+    fn next(&mut self) -> Result<Token, String> {
+        match self.tokens.get(self.position) {
+            Some(&(ref token, _)) => {
+                self.position += 1;
+                Ok(token.clone())
+            },
+            None => self.error("unexpected end of file".to_string()),
+        }
+    }
+    
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+    
+    fn is_ident(&self, ident: &str) -> bool {
+        match self.peek() {
+            Some(&Token::Ident(ref name)) => &name[] == ident,
+            _ => false,
+        }
+    }
+    
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), String> {
+        if self.is_symbol(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error(format!("expected {}", symbol))
+        }
+    }
+    
+    fn expect_ident(&mut self) -> Result<String, String> {
+        match try!(self.next()) {
+            Token::Ident(name) => Ok(name),
+            token => { self.position -= 1; self.error(format!("expected a name, found {:?}", token)) }
+        }
+    }
+    
+    fn expect_number(&mut self) -> Result<u64, String> {
+        let line = self.line();
+        match try!(self.next()) {
+            Token::Number(text) => parse_number(&text[], line).map(|(_, value)| { value }),
+            token => { self.position -= 1; self.error(format!("expected a number, found {:?}", token)) }
+        }
+    }
+    
+    fn parse_range(&mut self) -> Result<Option<(usize, usize)>, String> {
+        if !self.is_symbol('[') {
+            return Ok(None);
+        }
+        try!(self.expect_symbol('['));
+        let msb = try!(self.expect_number()) as usize;
+        let lsb = if self.is_symbol(':') {
+            try!(self.expect_symbol(':'));
+            try!(self.expect_number()) as usize
+        } else {
+            msb
+        };
+        try!(self.expect_symbol(']'));
+        Ok(Some((msb, lsb)))
+    }
+    
+    fn parse_direction(&mut self) -> Option<SignalKind> {
+        let kind = if self.is_ident("input") {
+            SignalKind::Input
+        } else if self.is_ident("output") {
+            SignalKind::Output
+        } else if self.is_ident("wire") {
+            SignalKind::Wire
+        } else {
+            return None;
+        };
+        self.position += 1;
+        if kind != SignalKind::Wire && self.is_ident("wire") {
+            self.position += 1;
+        }
+        Some(kind)
+    }
+    
+    fn parse_module(&mut self) -> Result<Module, String> {
+        if !self.is_ident("module") {
+            return self.error("expected module".to_string());
+        }
+        self.position += 1;
+        let mut module = Module {
+            name: try!(self.expect_ident()),
+            ports: Vec::new(),
+            signals: Vec::new(),
+            statements: Vec::new(),
+        };
+        
+        if self.is_symbol('(') {
+            try!(self.expect_symbol('('));
+            // Ports are either bare names, declared in the body, or declared in place.
+            let mut declaration = None;
+            while !self.is_symbol(')') {
+                if let Some(kind) = self.parse_direction() {
+                    declaration = Some((kind, try!(self.parse_range())));
+                }
+                let name = try!(self.expect_ident());
+                if let Some((kind, range)) = declaration {
+                    module.declare(kind, range, name.clone());
+                }
+                module.ports.push(name);
+                if !self.is_symbol(')') {
+                    try!(self.expect_symbol(','));
+                }
+            }
+            try!(self.expect_symbol(')'));
+        }
+        try!(self.expect_symbol(';'));
+        
+        loop {
+            let line = self.line();
+            if self.is_ident("endmodule") {
+                self.position += 1;
+                return Ok(module);
+            }
+            
+            if let Some(kind) = self.parse_direction() {
+                let range = try!(self.parse_range());
+                loop {
+                    let name = try!(self.expect_ident());
+                    module.declare(kind, range, name);
+                    if self.is_symbol(';') {
+                        break;
+                    }
+                    try!(self.expect_symbol(','));
+                }
+                try!(self.expect_symbol(';'));
+                continue;
+            }
+            
+            let keyword = try!(self.expect_ident());
+            if &keyword[] == "assign" {
+                loop {
+                    let target = try!(self.parse_expr());
+                    try!(self.expect_symbol('='));
+                    let value = try!(self.parse_expr());
+                    module.statements.push(Statement::Assign(target, value, line));
+                    if self.is_symbol(';') {
+                        break;
+                    }
+                    try!(self.expect_symbol(','));
+                }
+                try!(self.expect_symbol(';'));
+            } else if GATE_KINDS.iter().any(|kind| { *kind == &keyword[] }) {
+                try!(self.skip_delay());
+                loop {
+                    if !self.is_symbol('(') {
+                        try!(self.expect_ident()); // The instance name is optional
+                    }
+                    try!(self.expect_symbol('('));
+                    let mut terminals = vec![try!(self.parse_expr())];
+                    while self.is_symbol(',') {
+                        try!(self.expect_symbol(','));
+                        terminals.push(try!(self.parse_expr()));
+                    }
+                    try!(self.expect_symbol(')'));
+                    module.statements.push(Statement::Gate(keyword.clone(), terminals, line));
+                    if self.is_symbol(';') {
+                        break;
+                    }
+                    try!(self.expect_symbol(','));
+                }
+                try!(self.expect_symbol(';'));
+            } else if UNSUPPORTED_KEYWORDS.iter().any(|unsupported| { *unsupported == &keyword[] }) {
+                return Err(format!("line {}: {} is not supported, only gate-level Verilog", line, keyword));
+            } else {
+                if self.is_symbol('#') {
+                    return self.error(format!("parameters of {} are not supported", keyword));
+                }
+                match self.peek() {
+                    Some(&Token::Ident(_)) => {},
+                    _ => { return Err(format!("line {}: {} is not supported", line, keyword)); }
+                }
+                try!(self.expect_ident());
+                let connections = try!(self.parse_connections());
+                try!(self.expect_symbol(';'));
+                module.statements.push(Statement::Instance(keyword, connections, line));
+            }
+        }
+    }
+    
+    // Gate delays are accepted, but the delay library decides the timing.
+    fn skip_delay(&mut self) -> Result<(), String> {
+        if !self.is_symbol('#') {
+            return Ok(());
+        }
+        try!(self.expect_symbol('#'));
+        if self.is_symbol('(') {
+            while !self.is_symbol(')') {
+                try!(self.next());
+            }
+            try!(self.expect_symbol(')'));
+        } else {
+            try!(self.expect_number());
+        }
+        Ok(())
+    }
+    
+    fn parse_connections(&mut self) -> Result<Vec<Connection>, String> {
+        let mut connections = Vec::new();
+        try!(self.expect_symbol('('));
+        while !self.is_symbol(')') {
+            if self.is_symbol('.') {
+                try!(self.expect_symbol('.'));
+                let port = try!(self.expect_ident());
+                try!(self.expect_symbol('('));
+                let expr = if self.is_symbol(')') { None } else { Some(try!(self.parse_expr())) };
+                try!(self.expect_symbol(')'));
+                connections.push(Connection::Named(port, expr));
+            } else {
+                connections.push(Connection::Positional(try!(self.parse_expr())));
+            }
+            if !self.is_symbol(')') {
+                try!(self.expect_symbol(','));
+            }
+        }
+        try!(self.expect_symbol(')'));
+        Ok(connections)
+    }
+    
+    // From loosest to tightest: |, ^, &, then ~.
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        self.parse_binary(0)
+    }
+    
+    fn parse_binary(&mut self, level: usize) -> Result<Expr, String> {
+        let operators = ['|', '^', '&'];
+        if level == operators.len() {
+            return self.parse_unary();
+        }
+        
+        let mut expr = try!(self.parse_binary(level + 1));
+        while self.is_symbol(operators[level]) {
+            try!(self.expect_symbol(operators[level]));
+            let right = try!(self.parse_binary(level + 1));
+            expr = Expr::Binary(operators[level], Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+    
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.is_symbol('~') {
+            try!(self.expect_symbol('~'));
+            return Ok(Expr::Not(Box::new(try!(self.parse_unary()))));
+        }
+        
+        let line = self.line();
+        match try!(self.next()) {
+            Token::Symbol('(') => {
+                let expr = try!(self.parse_expr());
+                try!(self.expect_symbol(')'));
+                Ok(expr)
+            },
+            Token::Symbol('{') => {
+                let mut parts = vec![try!(self.parse_expr())];
+                while self.is_symbol(',') {
+                    try!(self.expect_symbol(','));
+                    parts.push(try!(self.parse_expr()));
+                }
+                try!(self.expect_symbol('}'));
+                Ok(Expr::Concat(parts))
+            },
+            Token::Number(text) => {
+                let (width, value) = try!(parse_number(&text[], line));
+                let width = match width {
+                    Some(width) => width,
+                    None if value <= 1 => 1,
+                    None => { return Err(format!("line {}: {} needs a width", line, text)); }
+                };
+                if width > 64 {
+                    return Err(format!("line {}: {} is too wide", line, text));
+                }
+                Ok(Expr::Constant(range(0, width).map(|bit| { (value >> bit) & 1 == 1 }).collect()))
+            },
+            Token::Ident(name) => {
+                let range = try!(self.parse_range());
+                Ok(Expr::Signal(name, range))
+            },
+            token => Err(format!("line {}: unexpected {:?}", line, token)),
+        }
+    }
+}
+
+fn parse_verilog(source: &str) -> Result<Vec<Module>, String> {
+    let mut parser = Parser {
+        tokens: try!(lex(source)),
+        position: 0,
+    };
+    let mut modules = Vec::new();
+    while parser.peek().is_some() {
+        modules.push(try!(parser.parse_module()));
+    }
+    Ok(modules)
+}
+
+/// The ports of an elaborated module, least significant bit first.
+pub struct VerilogModule {
+    pub ports: HashMap<String, Vec<NodeIndex>>,
+}
+
+impl VerilogModule {
+    pub fn port(&self, name: &str) -> Option<Vec<NodeIndex>> {
+        self.ports.get(name).map(|nodes| { nodes.clone() })
+    }
+}
+
+// Every signal of the module being elaborated, by name: its lsb and its nodes.
+type Signals = HashMap<String, (usize, Vec<NodeIndex>)>;
+
+struct Elaborator<'m> {
+    modules: &'m [Module],
+    stack: Vec<String>, // The modules being elaborated, to catch a module containing itself
+}
+
+impl<'m> Elaborator<'m> {
+    fn elaborate(&mut self, module: &'m Module, creator: &mut NodeCreator) -> Result<HashMap<String, Vec<NodeIndex>>, String> {
+        if self.stack.contains(&module.name) {
+            return Err(format!("module {} contains itself", module.name));
+        }
+        self.stack.push(module.name.clone());
+        creator.begin_component(&module.name[]);
+        let ports = self.elaborate_body(module, creator);
+        creator.end_component();
+        self.stack.pop();
+        ports
+    }
+    
+    fn elaborate_body(&mut self, module: &'m Module, creator: &mut NodeCreator) -> Result<HashMap<String, Vec<NodeIndex>>, String> {
+        let mut signals = HashMap::new();
+        for signal in module.signals.iter() {
+            let nodes : Vec<NodeIndex> = range(0, signal.width).map(|bit| {
+                if signal.width == 1 && signal.lsb == 0 {
+                    creator.new_named_node(&signal.name[])
+                } else {
+                    creator.new_named_node(&format!("{}[{}]", signal.name, signal.lsb + bit)[])
+                }
+            }).collect();
+            signals.insert(signal.name.clone(), (signal.lsb, nodes));
+        }
+        
+        for statement in module.statements.iter() {
+            try!(self.elaborate_statement(statement, &signals, creator));
+        }
+        
+        let mut ports = HashMap::new();
+        for port in module.ports.iter() {
+            match module.signal(&port[]) {
+                Some(signal) if signal.kind != SignalKind::Wire => {
+                    ports.insert(port.clone(), signals.get(port).unwrap().1.clone());
+                },
+                _ => { return Err(format!("port {} of {} is not declared an input or output", port, module.name)); }
+            }
+        }
+        
+        Ok(ports)
+    }
+    
+    fn elaborate_statement(&mut self, statement: &Statement, signals: &Signals, creator: &mut NodeCreator) -> Result<(), String> {
+        match *statement {
+            Statement::Assign(ref target, ref value, line) => {
+                let target = try!(signal_nodes(target, signals, line));
+                let value = try!(elaborate_expr(value, signals, line, creator));
+                if target.len() != value.len() {
+                    return Err(format!("line {}: assigning {} bits to {}", line, value.len(), target.len()));
+                }
+                creator.multiwire(&value[], &target[]);
+            },
+            Statement::Gate(ref kind, ref terminals, line) => {
+                if terminals.len() < 2 {
+                    return Err(format!("line {}: {} needs an output and an input", line, kind));
+                }
+                let mut nodes = Vec::new();
+                for terminal in terminals.iter() {
+                    let terminal = try!(elaborate_expr(terminal, signals, line, creator));
+                    if terminal.len() != 1 {
+                        return Err(format!("line {}: gate terminals must be one bit", line));
+                    }
+                    nodes.push(terminal[0]);
+                }
+                
+                match &kind[] {
+                    "not" | "buf" => {
+                        // Every terminal but the last is an output.
+                        let input = nodes[nodes.len() - 1];
+                        let output = if &kind[] == "not" {
+                            invert(input, creator)
+                        } else {
+                            input
+                        };
+                        for node in nodes[..nodes.len() - 1].iter() {
+                            creator.wire(output, *node);
+                        }
+                    },
+                    _ => {
+                        if nodes.len() < 3 {
+                            return Err(format!("line {}: {} needs two inputs", line, kind));
+                        }
+                        let operator = match &kind[] {
+                            "and" | "nand" => '&',
+                            "or" | "nor" => '|',
+                            _ => '^',
+                        };
+                        let mut output = nodes[1];
+                        for input in nodes[2..].iter() {
+                            output = binary_gate(operator, output, *input, creator);
+                        }
+                        if kind.starts_with("n") || &kind[] == "xnor" {
+                            output = invert(output, creator);
+                        }
+                        creator.wire(output, nodes[0]);
+                    }
+                }
+            },
+            Statement::Instance(ref name, ref connections, line) => {
+                let modules = self.modules;
+                let child = match modules.iter().find(|child| { child.name == *name }) {
+                    Some(child) => child,
+                    None => { return Err(format!("line {}: no module named {}", line, name)); }
+                };
+                let ports = try!(self.elaborate(child, creator));
+                
+                for (index, connection) in connections.iter().enumerate() {
+                    let (port, expr) = match *connection {
+                        Connection::Named(ref port, Some(ref expr)) => (port.clone(), expr),
+                        Connection::Named(_, None) => { continue; }, // Left unconnected
+                        Connection::Positional(ref expr) => {
+                            match child.ports.get(index) {
+                                Some(port) => (port.clone(), expr),
+                                None => { return Err(format!("line {}: {} has only {} ports", line, name, child.ports.len())); }
+                            }
+                        },
+                    };
+                    let port_nodes = match ports.get(&port) {
+                        Some(nodes) => nodes,
+                        None => { return Err(format!("line {}: {} has no port {}", line, name, port)); }
+                    };
+                    let nodes = try!(elaborate_expr(expr, signals, line, creator));
+                    if nodes.len() != port_nodes.len() {
+                        return Err(format!("line {}: connecting {} bits to port {} of {} bits", line, nodes.len(), port, port_nodes.len()));
+                    }
+                    creator.multiwire(&nodes[], &port_nodes[]);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+// The nodes an assignment can drive: signals, parts of signals, and concatenations of them.
+fn signal_nodes(expr: &Expr, signals: &Signals, line: usize) -> Result<Vec<NodeIndex>, String> {
+    match *expr {
+        Expr::Signal(ref name, select) => select_bits(name, select, signals, line),
+        Expr::Concat(ref parts) => {
+            let mut nodes = Vec::new();
+            for part in parts.iter().rev() {
+                nodes.push_all(&try!(signal_nodes(part, signals, line))[]);
+            }
+            Ok(nodes)
+        },
+        _ => Err(format!("line {}: can only assign to signals", line)),
+    }
+}
+
+fn select_bits(name: &str, select: Option<(usize, usize)>, signals: &Signals, line: usize) -> Result<Vec<NodeIndex>, String> {
+    let &(signal_lsb, ref nodes) = match signals.get(name) {
+        Some(signal) => signal,
+        None => { return Err(format!("line {}: undeclared signal {}", line, name)); }
+    };
+    match select {
+        None => Ok(nodes.clone()),
+        Some((msb, lsb)) => {
+            let (high, low) = if msb >= lsb { (msb, lsb) } else { (lsb, msb) };
+            if low < signal_lsb || high - signal_lsb >= nodes.len() {
+                return Err(format!("line {}: {}[{}:{}] is out of range", line, name, msb, lsb));
+            }
+            Ok(nodes[low - signal_lsb..high - signal_lsb + 1].to_vec())
+        }
+    }
+}
+
+fn elaborate_expr(expr: &Expr, signals: &Signals, line: usize, creator: &mut NodeCreator) -> Result<Vec<NodeIndex>, String> {
+    match *expr {
+        Expr::Signal(..) => signal_nodes(expr, signals, line),
+        Expr::Constant(ref bits) => {
+            Ok(bits.iter().map(|bit| { ConstantBit::new(*bit, creator).node }).collect())
+        },
+        Expr::Not(ref inner) => {
+            let inputs = try!(elaborate_expr(&**inner, signals, line, creator));
+            Ok(inputs.iter().map(|input| { invert(*input, creator) }).collect())
+        },
+        Expr::Binary(operator, ref left, ref right) => {
+            let left = try!(elaborate_expr(&**left, signals, line, creator));
+            let right = try!(elaborate_expr(&**right, signals, line, creator));
+            if left.len() != right.len() {
+                return Err(format!("line {}: {} bits {} {} bits", line, left.len(), operator, right.len()));
+            }
+            Ok(left.iter().zip(right.iter()).map(|(a, b)| { binary_gate(operator, *a, *b, creator) }).collect())
+        },
+        Expr::Concat(ref parts) => {
+            let mut nodes = Vec::new();
+            for part in parts.iter().rev() {
+                nodes.push_all(&try!(elaborate_expr(part, signals, line, creator))[]);
+            }
+            Ok(nodes)
+        },
+    }
+}
+
+/// Builds the module `top` from gate-level Verilog source, along with the modules it instantiates.
+pub fn read_verilog(source: &str, top: &str, creator: &mut NodeCreator) -> Result<VerilogModule, String> {
+    let modules = try!(parse_verilog(source));
+    let module = match modules.iter().find(|module| { &module.name[] == top }) {
+        Some(module) => module,
+        None => { return Err(format!("no module named {}", top)); }
+    };
+    
+    let mut elaborator = Elaborator {
+        modules: &modules[],
+        stack: Vec::new(),
+    };
+    let ports = try!(elaborator.elaborate(module, creator));
+    Ok(VerilogModule {
+        ports: ports,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator};
+    use logic_gates::NotGate;
+    use adder::Adder;
+    use clock::{Clock, ClockParams};
+    use truth_table::check_truth_table;
+    use super::{write_verilog, read_verilog};
+    
+    #[test]
+    fn test_not_gate() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let not = NotGate::new(&mut creator);
+        
+        let verilog = write_verilog("not_gate", &creator, &[("in", &[not.input][])], &[("out", &[not.output][])]).unwrap();
+        assert_eq!(&verilog[], "module not_gate(in, out);
+  input in;
+  output out;
+  nand g0(out, in, in);
+endmodule
+");
+    }
+    
+    #[test]
+    fn test_buses() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let adder = Adder::new(&mut creator);
+        
+        let verilog = write_verilog("full_adder", &creator,
+            &[("operands", &[adder.a, adder.b][]), ("carry_in", &[adder.carry_in][])],
+            &[("sum", &[adder.sum][]), ("carry_out", &[adder.carry_out][])]).unwrap();
+        assert!(verilog.contains("input [1:0] operands;"));
+        assert!(verilog.contains("(sum, "));
+        assert!(verilog.contains(", operands[0], "));
+    }
+    
+    #[test]
+    fn test_clocks_are_rejected() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        Clock::new(ClockParams::square(10), &mut creator);
+        assert!(write_verilog("clock", &creator, &[], &[]).is_err());
+    }
+    
+    #[test]
+    fn test_aliased_ports() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let not = NotGate::new(&mut creator);
+        
+        let verilog = write_verilog("not_gate", &creator, &[("in", &[not.input][])], &[("out", &[not.output][]), ("echo", &[not.input][])]).unwrap();
+        assert!(verilog.contains("  assign echo = in;\n"));
+        
+        let error = write_verilog("not_gate", &creator, &[("in", &[not.input][]), ("again", &[not.input][])], &[("out", &[not.output][])]).err();
+        assert_eq!(error, Some("inputs in and again are on the same net".to_string()));
+    }
+    
+    #[test]
+    fn test_read_assigns() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_verilog("
+                module half_adder(a, b, sum, carry);
+                  input a, b;
+                  output sum, carry;
+                  assign sum = a ^ b;
+                  assign carry = a & b;
+                endmodule
+            ", "half_adder", creator).unwrap();
+            
+            (vec![module.port("a").unwrap()[0], module.port("b").unwrap()[0]],
+             vec![module.port("sum").unwrap()[0], module.port("carry").unwrap()[0]])
+        }, &[
+            (&[0,0], &[0,0]),
+            (&[1,0], &[1,0]),
+            (&[0,1], &[1,0]),
+            (&[1,1], &[0,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_read_instances_and_gates() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_verilog("
+                module half_adder(input a, b, output sum, carry);
+                  xor (sum, a, b);
+                  and (carry, a, b);
+                endmodule
+                
+                /* Two half adders, with gate names and delays that are ignored */
+                module full_adder(input a, b, cin, output s, cout);
+                  wire partial, c1, c2;
+                  half_adder first(.a(a), .b(b), .sum(partial), .carry(c1));
+                  half_adder second(partial, cin, s, c2);
+                  or #2 either(cout, c1, c2);
+                endmodule
+            ", "full_adder", creator).unwrap();
+            
+            let port = |name: &str| { module.port(name).unwrap()[0] };
+            (vec![port("a"), port("b"), port("cin")], vec![port("s"), port("cout")])
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,0,0], &[1,0]),
+            (&[0,1,0], &[1,0]),
+            (&[1,1,0], &[0,1]),
+            (&[0,0,1], &[1,0]),
+            (&[1,0,1], &[0,1]),
+            (&[0,1,1], &[0,1]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_read_buses() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_verilog("
+                module buses(input [1:0] a, output [1:0] swapped, output [1:0] inverted);
+                  assign swapped = {a[0], a[1]};
+                  assign inverted = ~a & 2'b11;
+                endmodule
+            ", "buses", creator).unwrap();
+            
+            let mut outputs = module.port("swapped").unwrap();
+            outputs.push_all(&module.port("inverted").unwrap()[]);
+            (module.port("a").unwrap(), outputs)
+        }, &[
+            (&[0,0], &[0,0,1,1]),
+            (&[1,0], &[0,1,0,1]),
+            (&[0,1], &[1,0,1,0]),
+            (&[1,1], &[1,1,0,0]),
+        ]);
+    }
+    
+    #[test]
+    fn test_round_trip() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let adder = Adder::new(&mut creator);
+        let verilog = write_verilog("adder", &creator,
+            &[("operands", &[adder.a, adder.b][]), ("carry_in", &[adder.carry_in][])],
+            &[("sum", &[adder.sum][]), ("carry_out", &[adder.carry_out][])]).unwrap();
+        
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_verilog(&verilog[], "adder", creator).unwrap();
+            let mut inputs = module.port("operands").unwrap();
+            inputs.push_all(&module.port("carry_in").unwrap()[]);
+            let mut outputs = module.port("sum").unwrap();
+            outputs.push_all(&module.port("carry_out").unwrap()[]);
+            (inputs, outputs)
+        }, &[
+            (&[0,0,0], &[0,0]),
+            (&[1,1,0], &[0,1]),
+            (&[1,0,1], &[0,1]),
+            (&[0,0,1], &[1,0]),
+            (&[1,1,1], &[1,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_read_errors() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let error = read_verilog("module m(a, y);\n  input a; output y;\n  assign y = a & b;\nendmodule\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 3: undeclared signal b".to_string()));
+        
+        let error = read_verilog("module m(a);\n  input a;\n  reg r;\nendmodule\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 3: reg is not supported, only gate-level Verilog".to_string()));
+        
+        let error = read_verilog("module m(y);\n  output [79:0] y;\n  assign y = 80'hffffffffffffffffffff;\nendmodule\n", "m", &mut creator).err();
+        assert_eq!(error, Some("line 3: 80'hffffffffffffffffffff does not fit in 64 bits".to_string()));
+        
+        // The failed reads left no module open, so the one m elaborated before this is its sibling
+        let module = read_verilog("module m(a, y);\n  input a; output y;\n  assign y = a;\nendmodule\n", "m", &mut creator).unwrap();
+        let a = module.port("a").unwrap()[0];
+        assert!(creator.node_names().iter().any(|&(node, ref name)| { node == a && &name[] == "m1.a" }));
+    }
+}
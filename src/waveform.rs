@@ -0,0 +1,357 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::cmp::{min, max};
+
+use sim::{NodeIndex, NodeCollection, LineState, bus_value};
+
+/// The changes of chosen nodes and buses over a run, for drawing as a timing diagram.
+pub struct Trace {
+    signals: Vec<(String, Rc<RefCell<Vec<(u64, Vec<LineState>)>>>)>,
+}
+
+impl Trace {
+    pub fn new() -> Trace {
+        Trace {
+            signals: Vec::new(),
+        }
+    }
+    
+    /// Starts recording a node, or a bus given least significant bit first, under `name`.
+    /// The nodes must already have been absorbed.
+    pub fn record(&mut self, name: &str, nodes: &[NodeIndex], c: &mut NodeCollection) {
+        let initial : Vec<LineState> = nodes.iter().map(|node| { node.read(c) }).collect();
+        let changes = Rc::new(RefCell::new(vec![(c.current_tick, initial)]));
+        let recorder = changes.clone();
+        c.watch_bus(nodes, move |tick, _, new| {
+            recorder.borrow_mut().push((tick, new.to_vec()));
+        });
+        self.signals.push((name.to_string(), changes));
+    }
+    
+    // What a signal held from `from` until `to`, as runs of (start, end, states).
+    // Changes that were undone within the same tick are left out.
+    fn segments(&self, signal: usize, from: u64, to: u64) -> Vec<(u64, u64, Vec<LineState>)> {
+        let changes = self.signals[signal].1.borrow();
+        let mut segments : Vec<(u64, u64, Vec<LineState>)> = Vec::new();
+        for (index, &(tick, ref states)) in changes.iter().enumerate() {
+            let next_tick = changes.get(index + 1).map(|&(next_tick, _)| { next_tick }).unwrap_or(to);
+            let (start, end) = (max(tick, from), min(next_tick, to));
+            if start >= end {
+                continue;
+            }
+            if let Some(last) = segments.last_mut() {
+                if last.2 == *states && last.1 == start {
+                    last.1 = end;
+                    continue;
+                }
+            }
+            segments.push((start, end, states.clone()));
+        }
+        segments
+    }
+    
+    // The states of a signal once everything at `tick` has played.
+    fn states_at(&self, signal: usize, tick: u64) -> Vec<LineState> {
+        let changes = self.signals[signal].1.borrow();
+        let mut states = &changes[0].1;
+        for &(change_tick, ref new_states) in changes.iter() {
+            if change_tick > tick {
+                break;
+            }
+            states = new_states;
+        }
+        states.clone()
+    }
+}
+
+// How a bus's value is written: hex if every line is Low or High, otherwise x for
+// any conflict or z for floating lines.
+fn bus_text(states: &[LineState]) -> String {
+    match bus_value(states) {
+        Some(value) => format!("{:x}", value),
+        None if states.iter().any(|state| { *state == LineState::Conflict }) => "x".to_string(),
+        None => "z".to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;")
+}
+
+// A round spacing for time axis labels that gives at most ten of them.
+fn axis_step(span: u64) -> u64 {
+    let mut magnitude = 1;
+    loop {
+        for multiple in [1, 2, 5].iter() {
+            if span / (magnitude * *multiple) <= 10 {
+                return magnitude * *multiple;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+const NAME_WIDTH: f64 = 100.0;
+const ROW_HEIGHT: f64 = 30.0;
+const WAVE_HEIGHT: f64 = 20.0;
+const AXIS_HEIGHT: f64 = 30.0;
+
+/// Draws the trace from tick `from` to `to` as an SVG timing diagram `width` pixels wide,
+/// plus room for the names. Single nodes are drawn as levels, buses as their values in hex;
+/// floating stretches are dashed blue and conflicts are red.
+pub fn render_svg(trace: &Trace, from: u64, to: u64, width: u32) -> String {
+    assert!(to > from, "the trace must cover at least one tick");
+    let scale = width as f64 / (to - from) as f64;
+    let x = |tick: u64| { NAME_WIDTH + (tick - from) as f64 * scale };
+    let total_height = ROW_HEIGHT * trace.signals.len() as f64 + AXIS_HEIGHT;
+    
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" font-family=\"monospace\" font-size=\"12\">\n",
+        NAME_WIDTH + width as f64 + 10.0, total_height);
+    svg.push_str("<style>\n\
+        .wave { stroke: black; fill: none; }\n\
+        .bus { stroke: black; fill: #efe; }\n\
+        .floating { stroke: #36c; stroke-dasharray: 4,3; fill: none; }\n\
+        .conflict { stroke: #d22; fill: #f99; }\n\
+        .axis { stroke: gray; }\n\
+        </style>\n");
+    
+    for (row, &(ref name, ref changes)) in trace.signals.iter().enumerate() {
+        let high = ROW_HEIGHT * row as f64 + 5.0;
+        let low = high + WAVE_HEIGHT;
+        let middle = high + WAVE_HEIGHT / 2.0;
+        svg.push_str(&format!("<text x=\"5\" y=\"{:.1}\">{}</text>\n", middle + 4.0, escape(&name[]))[]);
+        
+        let bits = changes.borrow()[0].1.len();
+        let mut previous = None;
+        for (start, end, states) in trace.segments(row, from, to).into_iter() {
+            let (start_x, end_x) = (x(start), x(end));
+            if bits == 1 {
+                match states[0] {
+                    LineState::Low | LineState::High => {
+                        let level = if states[0] == LineState::High { high } else { low };
+                        if let Some(LineState::Low) = previous {
+                            if states[0] == LineState::High {
+                                svg.push_str(&format!("<line class=\"wave\" x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\"/>\n", start_x, low, high)[]);
+                            }
+                        }
+                        if let Some(LineState::High) = previous {
+                            if states[0] == LineState::Low {
+                                svg.push_str(&format!("<line class=\"wave\" x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\"/>\n", start_x, high, low)[]);
+                            }
+                        }
+                        svg.push_str(&format!("<line class=\"wave\" x1=\"{:.1}\" y1=\"{2:.1}\" x2=\"{:.1}\" y2=\"{2:.1}\"/>\n", start_x, end_x, level)[]);
+                    },
+                    LineState::Floating => {
+                        svg.push_str(&format!("<line class=\"floating\" x1=\"{:.1}\" y1=\"{2:.1}\" x2=\"{:.1}\" y2=\"{2:.1}\"/>\n", start_x, end_x, middle)[]);
+                    },
+                    LineState::Conflict => {
+                        svg.push_str(&format!("<rect class=\"conflict\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"/>\n", start_x, high, end_x - start_x, WAVE_HEIGHT)[]);
+                    },
+                }
+                previous = Some(states[0]);
+            } else {
+                let class = if states.iter().any(|state| { *state == LineState::Conflict }) {
+                    "conflict"
+                } else if states.iter().any(|state| { *state == LineState::Floating }) {
+                    "floating"
+                } else {
+                    "bus"
+                };
+                // A long hexagon, pinched where the value changes
+                let slant = if end_x - start_x < 6.0 { (end_x - start_x) / 2.0 } else { 3.0 };
+                svg.push_str(&format!("<polygon class=\"{}\" points=\"{:.1},{5:.1} {:.1},{6:.1} {:.1},{6:.1} {:.1},{5:.1} {3:.1},{7:.1} {2:.1},{7:.1}\"/>\n",
+                    class, start_x, start_x + slant, end_x - slant, end_x, middle, high, low)[]);
+                let text = bus_text(&states[]);
+                if end_x - start_x >= 8.0 * text.len() as f64 + 6.0 {
+                    svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n", (start_x + end_x) / 2.0, middle + 4.0, text)[]);
+                }
+            }
+        }
+    }
+    
+    let axis_y = ROW_HEIGHT * trace.signals.len() as f64 + 5.0;
+    svg.push_str(&format!("<line class=\"axis\" x1=\"{:.1}\" y1=\"{2:.1}\" x2=\"{:.1}\" y2=\"{2:.1}\"/>\n", x(from), x(to), axis_y)[]);
+    let step = axis_step(to - from);
+    let mut tick = (from + step - 1) / step * step;
+    while tick <= to {
+        svg.push_str(&format!("<line class=\"axis\" x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\"/>\n", x(tick), axis_y, axis_y + 4.0)[]);
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n", x(tick), axis_y + 16.0, tick)[]);
+        tick += step;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Prints the trace from tick `from` to `to` with a column for every `ticks_per_column`
+/// ticks. Single nodes are drawn like _/‾\_ with - where they float and X where they
+/// conflict; buses show their value in hex after a | wherever it changes. Each column
+/// shows the state at its first tick, so a column in which the signal changes more than
+/// once is drawn as | for a single node, or * for a bus, rather than hiding the pulse.
+pub fn render_ascii(trace: &Trace, from: u64, to: u64, ticks_per_column: u64) -> String {
+    assert!(to >= from, "the trace must not end before it starts");
+    assert!(ticks_per_column > 0);
+    let columns = max(1, ((to - from + ticks_per_column - 1) / ticks_per_column) as usize);
+    let name_width = trace.signals.iter().map(|&(ref name, _)| { name.chars().count() }).max().unwrap_or(0) + 1;
+    let pad = |text: &str| {
+        let mut padded = text.to_string();
+        for _ in range(text.chars().count(), name_width) {
+            padded.push(' ');
+        }
+        padded
+    };
+    
+    let mut text = String::new();
+    for (row, &(ref name, _)) in trace.signals.iter().enumerate() {
+        let samples : Vec<Vec<LineState>> = range(0, columns).map(|column| {
+            trace.states_at(row, from + column as u64 * ticks_per_column)
+        }).collect();
+        let busy : Vec<bool> = range(0, columns).map(|column| {
+            let start = from + column as u64 * ticks_per_column;
+            trace.segments(row, start, min(start + ticks_per_column, to)).len() > 2
+        }).collect();
+        
+        let mut line = pad(&name[]);
+        if samples[0].len() == 1 {
+            for column in range(0, columns) {
+                let previous = if column > 0 { Some(samples[column - 1][0]) } else { None };
+                line.push(match (previous, samples[column][0]) {
+                    _ if busy[column] => '|',
+                    (Some(LineState::Low), LineState::High) => '/',
+                    (Some(LineState::High), LineState::Low) => '\\',
+                    (_, LineState::Low) => '_',
+                    (_, LineState::High) => '‾',
+                    (_, LineState::Floating) => '-',
+                    (_, LineState::Conflict) => 'X',
+                });
+            }
+        } else {
+            let mut column = 0;
+            while column < columns {
+                if busy[column] {
+                    line.push('*');
+                    column += 1;
+                    continue;
+                }
+                let mut end = column + 1;
+                while end < columns && !busy[end] && samples[end] == samples[column] {
+                    end += 1;
+                }
+                let label : Vec<char> = format!("|{}", bus_text(&samples[column][])).chars().collect();
+                for offset in range(0, end - column) {
+                    line.push(if offset < label.len() { label[offset] } else { ' ' });
+                }
+                column = end;
+            }
+        }
+        text.push_str(line.trim_right());
+        text.push('\n');
+    }
+    
+    // Label every tenth column, where the label fits
+    let mut axis = pad("");
+    let mut column = 0;
+    while column < columns {
+        let label = (from + column as u64 * ticks_per_column).to_string();
+        axis.push_str(&label[]);
+        let next = column + 10;
+        for _ in range(column + label.len(), next) {
+            axis.push(' ');
+        }
+        column = if column + label.len() > next { column + label.len() } else { next };
+    }
+    text.push_str(axis.trim_right());
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay, bus_states};
+    use pin::Pin;
+    use super::{Trace, render_svg, render_ascii};
+    
+    #[test]
+    fn test_svg() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let drivers = [Pin::new(&mut creator), Pin::new(&mut creator)];
+        creator.link(drivers[0].node, drivers[1].node, PropogationDelay(0));
+        let bus : Vec<Pin> = range(0, 8).map(|_| { Pin::new(&mut creator) }).collect();
+        c.absorb(creator);
+        
+        let mut trace = Trace::new();
+        trace.record("shared", &[drivers[0].node], &mut c);
+        let bus_nodes : Vec<_> = bus.iter().map(|pin| { pin.node }).collect();
+        trace.record("data<7:0>", &bus_nodes[], &mut c);
+        
+        drivers[0].node.write_later(LineState::High, PropogationDelay(100), &mut c);
+        drivers[0].node.write_later(LineState::Low, PropogationDelay(300), &mut c);
+        drivers[1].node.write_later(LineState::High, PropogationDelay(600), &mut c);
+        for (pin, state) in bus.iter().zip(bus_states(0xa5, 8).into_iter()) {
+            pin.node.write_later(state, PropogationDelay(200), &mut c);
+        }
+        while c.play() {}
+        // Nothing reads as floating unless it is forced to
+        c.play_until(800);
+        c.force(drivers[0].node, LineState::Floating);
+        c.force(bus[3].node, LineState::Floating);
+        while c.play() {}
+        
+        let svg = render_svg(&trace, 0, 1000, 500);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"610\" height=\"90\""));
+        assert!(svg.contains(">data&lt;7:0&gt;</text>"));
+        // Low until 100, high until 300, low until 600, driven both ways until 800, then floating
+        assert!(svg.contains("<line class=\"wave\" x1=\"100.0\" y1=\"25.0\" x2=\"150.0\" y2=\"25.0\"/>"));
+        assert!(svg.contains("<line class=\"wave\" x1=\"150.0\" y1=\"25.0\" x2=\"150.0\" y2=\"5.0\"/>"));
+        assert!(svg.contains("<line class=\"wave\" x1=\"150.0\" y1=\"5.0\" x2=\"250.0\" y2=\"5.0\"/>"));
+        assert!(svg.contains("<line class=\"wave\" x1=\"250.0\" y1=\"5.0\" x2=\"250.0\" y2=\"25.0\"/>"));
+        assert!(svg.contains("<rect class=\"conflict\" x=\"400.0\" y=\"5.0\" width=\"100.0\" height=\"20.0\"/>"));
+        assert!(svg.contains("<line class=\"floating\" x1=\"500.0\" y1=\"15.0\" x2=\"600.0\" y2=\"15.0\"/>"));
+        // The bus reads 0 until its pins are written at 200, and floats once a line is forced to
+        assert!(svg.contains("<polygon class=\"bus\" points=\"100.0,45.0 103.0,35.0 197.0,35.0 200.0,45.0 197.0,55.0 103.0,55.0\"/>"));
+        assert!(svg.contains("<polygon class=\"floating\" points=\"500.0,45.0 503.0,35.0 597.0,35.0 600.0,45.0 597.0,55.0 503.0,55.0\"/>"));
+        assert!(svg.contains(">0</text>"));
+        assert!(svg.contains(">a5</text>"));
+        assert!(svg.contains(">z</text>"));
+        assert!(svg.contains("<text x=\"350.0\" y=\"81.0\" text-anchor=\"middle\">500</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+    
+    #[test]
+    fn test_ascii() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let line = Pin::new(&mut creator);
+        let bus : Vec<Pin> = range(0, 4).map(|_| { Pin::new(&mut creator) }).collect();
+        c.absorb(creator);
+        
+        let mut trace = Trace::new();
+        trace.record("a", &[line.node], &mut c);
+        let bus_nodes : Vec<_> = bus.iter().map(|pin| { pin.node }).collect();
+        trace.record("bus", &bus_nodes[], &mut c);
+        
+        line.node.write_later(LineState::High, PropogationDelay(20), &mut c);
+        line.node.write_later(LineState::Low, PropogationDelay(50), &mut c);
+        for (value, delay) in vec![(3, 20), (0xc, 40)].into_iter() {
+            for (pin, state) in bus.iter().zip(bus_states(value, 4).into_iter()) {
+                pin.node.write_later(state, PropogationDelay(delay), &mut c);
+            }
+        }
+        while c.play() {}
+        c.play_until(70);
+        c.force(line.node, LineState::Floating);
+        c.force(bus[0].node, LineState::Floating);
+        
+        assert_eq!(render_ascii(&trace, 0, 80, 5), "\
+a   ____/‾‾‾‾‾\\___--
+bus |0  |3  |c    |z
+    0         50
+");
+        // Going high and back low within the first column, the bus taking three values
+        assert_eq!(render_ascii(&trace, 0, 80, 60), "\
+a   |_
+bus *|
+    0
+");
+    }
+}
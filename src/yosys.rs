@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use serialize::json;
+use serialize::json::Json;
+
+use sim::{NodeCreator, NodeIndex};
+use logic_gates::{NandGate, NotGate, binary_gate, invert};
+use mux::BitMux;
+use storage::DFlipFlop;
+use rom::ConstantBit;
+
+/// The ports of an imported module, least significant bit first.
+pub struct YosysModule {
+    pub ports: HashMap<String, Vec<NodeIndex>>,
+}
+
+impl YosysModule {
+    pub fn port(&self, name: &str) -> Option<Vec<NodeIndex>> {
+        self.ports.get(name).map(|nodes| { nodes.clone() })
+    }
+}
+
+// Yosys numbers every net bit. Each becomes a node that whatever uses the bit is wired to.
+struct Nets {
+    nodes: HashMap<u64, NodeIndex>,
+}
+
+impl Nets {
+    fn node(&mut self, bit: &Json, creator: &mut NodeCreator) -> Result<NodeIndex, String> {
+        if let Some(number) = bit.as_u64() {
+            if let Some(node) = self.nodes.get(&number) {
+                return Ok(*node);
+            }
+            let node = creator.new_node();
+            self.nodes.insert(number, node);
+            return Ok(node);
+        }
+        
+        match bit.as_string() {
+            Some("0") => Ok(ConstantBit::new(false, creator).node),
+            Some("1") => Ok(ConstantBit::new(true, creator).node),
+            Some("x") | Some("z") => Ok(creator.new_node()),
+            _ => Err(format!("bad bit {}", bit)),
+        }
+    }
+    
+    fn bus(&mut self, bits: Option<&Json>, creator: &mut NodeCreator) -> Result<Vec<NodeIndex>, String> {
+        let bits = try!(bits.and_then(|bits| { bits.as_array() }).ok_or("missing bits".to_string()));
+        let mut nodes = Vec::new();
+        for bit in bits.iter() {
+            nodes.push(try!(self.node(bit, creator)));
+        }
+        Ok(nodes)
+    }
+    
+    // Named nets get their node first, so that it carries the name.
+    fn name(&mut self, name: &str, bits: Option<&Json>, creator: &mut NodeCreator) {
+        let bits = match bits.and_then(|bits| { bits.as_array() }) {
+            Some(bits) => bits,
+            None => { return; }
+        };
+        for (index, bit) in bits.iter().enumerate() {
+            if let Some(number) = bit.as_u64() {
+                if !self.nodes.contains_key(&number) {
+                    let node = if bits.len() == 1 {
+                        creator.new_named_node(name)
+                    } else {
+                        creator.new_named_node(&format!("{}[{}]", name, index)[])
+                    };
+                    self.nodes.insert(number, node);
+                }
+            }
+        }
+    }
+}
+
+fn cell_pin(connections: &json::Object, pin: &str, nets: &mut Nets, creator: &mut NodeCreator) -> Result<NodeIndex, String> {
+    let bits = try!(nets.bus(connections.get(pin), creator).map_err(|message| { format!("pin {}: {}", pin, message) }));
+    if bits.len() != 1 {
+        return Err(format!("pin {} should be one bit", pin));
+    }
+    Ok(bits[0])
+}
+
+const ONE_INPUT_PINS: &'static [&'static str] = &["A", "Y"];
+const TWO_INPUT_PINS: &'static [&'static str] = &["A", "B", "Y"];
+const MUX_PINS: &'static [&'static str] = &["A", "B", "S", "Y"];
+const DFF_PINS: &'static [&'static str] = &["C", "D", "Q"];
+
+// Builds one cell, returning the names of its pins and their nodes.
+fn build_cell(kind: &str, creator: &mut NodeCreator) -> Result<(&'static [&'static str], Vec<NodeIndex>), String> {
+    // The two-input gates, as their base gate and whether B and Y are inverted
+    let (base, invert_b, invert_y) = match kind {
+        "$_BUF_" => {
+            let node = creator.new_node();
+            return Ok((ONE_INPUT_PINS, vec![node, node]));
+        },
+        "$_NOT_" => {
+            let not = NotGate::new(creator);
+            return Ok((ONE_INPUT_PINS, vec![not.input, not.output]));
+        },
+        "$_NAND_" => {
+            let nand = NandGate::new(creator);
+            return Ok((TWO_INPUT_PINS, vec![nand.a, nand.b, nand.output]));
+        },
+        "$_MUX_" => {
+            let mux = BitMux::new(creator);
+            return Ok((MUX_PINS, vec![mux.a, mux.b, mux.select, mux.output]));
+        },
+        "$_DFF_P_" | "$_DFF_N_" => {
+            let ff = DFlipFlop::new(creator);
+            let clock = if kind == "$_DFF_N_" {
+                let not = NotGate::new(creator);
+                creator.wire(not.output, ff.clock);
+                not.input
+            } else {
+                ff.clock
+            };
+            return Ok((DFF_PINS, vec![clock, ff.data, ff.q]));
+        },
+        "$_AND_" => ('&', false, false),
+        "$_OR_" => ('|', false, false),
+        "$_XOR_" => ('^', false, false),
+        "$_NOR_" => ('|', false, true),
+        "$_XNOR_" => ('^', false, true),
+        "$_ANDNOT_" => ('&', true, false),
+        "$_ORNOT_" => ('|', true, false),
+        _ => { return Err(format!("unsupported cell type {}", kind)); }
+    };
+    
+    let a = creator.new_node();
+    let b = creator.new_node();
+    let gate_b = if invert_b { invert(b, creator) } else { b };
+    let mut y = binary_gate(base, a, gate_b, creator);
+    if invert_y {
+        y = invert(y, creator);
+    }
+    Ok((TWO_INPUT_PINS, vec![a, b, y]))
+}
+
+/// Builds the module `top` of a netlist written by yosys's write_json, which must already
+/// be mapped to simple cells (as after synth; abc -g AND,NAND,OR,NOR,XOR,XNOR,ANDNOT,ORNOT,MUX).
+pub fn read_yosys_json(text: &str, top: &str, creator: &mut NodeCreator) -> Result<YosysModule, String> {
+    let root = try!(json::from_str(text).map_err(|error| { format!("bad JSON: {:?}", error) }));
+    let module = try!(root.find("modules").and_then(|modules| { modules.find(top) }).ok_or(format!("no module named {}", top)));
+    let ports = try!(module.find("ports").and_then(|ports| { ports.as_object() }).ok_or("module has no ports".to_string()));
+    let empty = json::Object::new();
+    let cells = module.find("cells").and_then(|cells| { cells.as_object() }).unwrap_or(&empty);
+    let netnames = module.find("netnames").and_then(|netnames| { netnames.as_object() }).unwrap_or(&empty);
+    
+    creator.begin_component(top);
+    let ports = build_module(ports, cells, netnames, creator);
+    creator.end_component();
+    ports.map(|ports| { YosysModule { ports: ports } })
+}
+
+fn build_module(ports: &json::Object, cells: &json::Object, netnames: &json::Object, creator: &mut NodeCreator) -> Result<HashMap<String, Vec<NodeIndex>>, String> {
+    let mut nets = Nets {
+        nodes: HashMap::new(),
+    };
+    
+    for (name, port) in ports.iter() {
+        nets.name(&name[], port.find("bits"), creator);
+    }
+    for (name, netname) in netnames.iter() {
+        if netname.find("hide_name").and_then(|hide| { hide.as_u64() }) != Some(1) {
+            nets.name(&name[], netname.find("bits"), creator);
+        }
+    }
+    
+    let mut port_nodes = HashMap::new();
+    for (name, port) in ports.iter() {
+        let nodes = try!(nets.bus(port.find("bits"), creator).map_err(|message| { format!("port {}: {}", name, message) }));
+        port_nodes.insert(name.clone(), nodes);
+    }
+    
+    for (name, cell) in cells.iter() {
+        let kind = try!(cell.find("type").and_then(|kind| { kind.as_string() }).ok_or(format!("cell {} has no type", name)));
+        let connections = try!(cell.find("connections").and_then(|connections| { connections.as_object() }).ok_or(format!("cell {} has no connections", name)));
+        
+        let (pins, nodes) = try!(build_cell(kind, creator).map_err(|message| { format!("cell {}: {}", name, message) }));
+        for (pin, node) in pins.iter().zip(nodes.iter()) {
+            let net = try!(cell_pin(connections, *pin, &mut nets, creator).map_err(|message| { format!("cell {}: {}", name, message) }));
+            creator.wire(net, *node);
+        }
+    }
+    
+    Ok(port_nodes)
+}
+
+#[cfg(test)]
+mod test {
+    use sim::{NodeCollection, NodeCreator, LineState, PropogationDelay, bus_states, read_bus};
+    use truth_table::check_truth_table;
+    use pin::Pin;
+    use super::read_yosys_json;
+    
+    #[test]
+    fn test_half_adder() {
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_yosys_json(include_str!("../fixtures/yosys/half_adder.json"), "half_adder", creator).unwrap();
+            let port = |name: &str| { module.port(name).unwrap()[0] };
+            (vec![port("a"), port("b")], vec![port("sum"), port("carry")])
+        }, &[
+            (&[0,0], &[0,0]),
+            (&[1,0], &[1,0]),
+            (&[0,1], &[1,0]),
+            (&[1,1], &[0,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_mux_and_constants() {
+        // y = s ? a[1] : ~a[0], z = a[0] | ~s, one = 1
+        check_truth_table(|creator: &mut NodeCreator| {
+            let module = read_yosys_json(include_str!("../fixtures/yosys/pick.json"), "pick", creator).unwrap();
+            let a = module.port("a").unwrap();
+            let port = |name: &str| { module.port(name).unwrap()[0] };
+            (vec![port("s"), a[0], a[1]], vec![port("y"), port("z"), port("one")])
+        }, &[
+            (&[0,0,0], &[1,1,1]),
+            (&[0,1,0], &[0,1,1]),
+            (&[1,0,1], &[1,0,1]),
+            (&[1,1,0], &[0,1,1]),
+            (&[1,0,0], &[0,0,1]),
+        ]);
+    }
+    
+    #[test]
+    fn test_flip_flops() {
+        let mut c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let module = read_yosys_json(include_str!("../fixtures/yosys/reg2.json"), "reg2", &mut creator).unwrap();
+        let clock = Pin::new(&mut creator);
+        let data : Vec<Pin> = range(0, 2).map(|_| { Pin::new(&mut creator) }).collect();
+        creator.wire(clock.node, module.port("clk").unwrap()[0]);
+        for (pin, input) in data.iter().zip(module.port("d").unwrap().iter()) {
+            creator.wire(pin.node, *input);
+        }
+        c.absorb(creator);
+        let q = module.port("q").unwrap();
+        let late = module.port("late").unwrap()[0];
+        
+        let write_data = |value: u64, delay: u32, c: &mut NodeCollection| {
+            for (pin, state) in data.iter().zip(bus_states(value, 2).iter()) {
+                pin.node.write_later(*state, PropogationDelay(delay), c);
+            }
+        };
+        
+        clock.node.write(LineState::Low, &mut c);
+        write_data(1, 0, &mut c);
+        clock.node.write_later(LineState::High, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!(read_bus(&q[], &c), Some(1));
+        
+        // The negative-edge flip-flop only takes d[0] when the clock falls
+        write_data(2, 0, &mut c);
+        clock.node.write_later(LineState::Low, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!(read_bus(&q[], &c), Some(1));
+        assert_eq!(late.read(&c), LineState::Low);
+        
+        clock.node.write_later(LineState::High, PropogationDelay(2000), &mut c);
+        while c.play() {}
+        assert_eq!(read_bus(&q[], &c), Some(2));
+    }
+    
+    #[test]
+    fn test_unsupported_cells() {
+        let c = NodeCollection::new();
+        let mut creator = NodeCreator::new(&c);
+        let error = read_yosys_json(r#"{"modules": {"m": {"ports": {}, "cells": {"c0": {"type": "$_DLATCH_P_", "connections": {}}}}}}"#, "m", &mut creator).err();
+        assert_eq!(error, Some("cell c0: unsupported cell type $_DLATCH_P_".to_string()));
+        
+        // The failed read left no module open, so the m before this one is its sibling
+        let module = read_yosys_json(r#"{"modules": {"m": {"ports": {"a": {"direction": "input", "bits": [2]}}}}}"#, "m", &mut creator).unwrap();
+        let a = module.port("a").unwrap()[0];
+        assert!(creator.node_names().iter().any(|&(node, ref name)| { node == a && &name[] == "m1.a" }));
+    }
+}